@@ -0,0 +1,116 @@
+//! This module contains tools for generating and solving many maps at once.
+//! With the `parallel` feature the work is spread across threads.
+//!
+//! Every item gets its own random number generator seeded from the batch seed and its index,
+//! so results are the same no matter how many threads are used.
+
+use crate::map::{self, Map};
+use crate::point::Point;
+use crate::solver::{self, GuessStrategy, Move};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+/// Derive the seed of the item at `index` from the batch `seed`, using a SplitMix64 step
+/// so that neighbouring items get unrelated seeds.
+fn item_seed(seed: u64, index: usize) -> u64 {
+    let mut z: u64 = seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Get the generator of the item at `index` in a batch with the given `seed`.
+pub(crate) fn item_rng(seed: u64, index: usize) -> StdRng {
+    StdRng::seed_from_u64(item_seed(seed, index))
+}
+
+/// Run `f` for each index up to `count` with a generator seeded for that item, in order.
+fn map_seeded<T, F>(count: usize, seed: u64, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, &mut StdRng) -> T + Send + Sync,
+{
+    let run = |index: usize| f(index, &mut item_rng(seed, index));
+
+    #[cfg(feature = "parallel")]
+    return (0..count).into_par_iter().map(run).collect();
+
+    #[cfg(not(feature = "parallel"))]
+    return (0..count).map(run).collect();
+}
+
+/// Generate `count` maps with the given dimensions and `total` number of mines,
+/// none of which have a mine at the initial `click`.
+/// The same `seed` always produces the same maps.
+///
+/// ```
+/// use casspir::{batch, point};
+/// let click = point::Point { x: 4, y: 4 };
+/// let maps = batch::generate_batch(9, 9, 10, click.clone(), 20, 42);
+/// assert_eq!(20, maps.len());
+/// assert!(maps == batch::generate_batch(9, 9, 10, click, 20, 42));
+/// ```
+pub fn generate_batch(
+    width: u16,
+    height: u16,
+    total: u32,
+    click: Point,
+    count: usize,
+    seed: u64,
+) -> Vec<Map> {
+    map_seeded(count, seed, |_, rng| {
+        map::generate_map_with_total_and_rng(width, height, total, click.clone(), rng)
+    })
+}
+
+/// Solve each of the `maps` like `solver::solve`, producing the moves for each map in order.
+/// The same `seed` always produces the same moves.
+pub fn solve_batch(maps: &[Map], seed: u64) -> Vec<VecDeque<Move>> {
+    map_seeded(maps.len(), seed, |index, rng| {
+        solver::solve_with_rng(&maps[index], GuessStrategy::LowestRisk, rng)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_is_deterministic() {
+        let click = Point { x: 8, y: 8 };
+        let mut maps = generate_batch(16, 16, 40, click.clone(), 12, 7);
+        for map in maps.iter_mut() {
+            map.flip(&click);
+        }
+
+        // Each item matches generating and solving it on its own.
+        let solutions = solve_batch(&maps, 7);
+        for (index, solution) in solutions.iter().enumerate() {
+            let mut rng = StdRng::seed_from_u64(item_seed(7, index));
+            assert!(
+                *solution
+                    == solver::solve_with_rng(&maps[index], GuessStrategy::LowestRisk, &mut rng)
+            );
+        }
+
+        // The same seeds give the same results, however many threads run them.
+        #[cfg(feature = "parallel")]
+        {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap();
+            assert!(solutions == pool.install(|| solve_batch(&maps, 7)));
+        }
+        assert!(solutions == solve_batch(&maps, 7));
+
+        // Different seeds give different maps.
+        assert!(
+            generate_batch(16, 16, 40, click.clone(), 12, 7)
+                != generate_batch(16, 16, 40, click, 12, 8)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Benchmarks the solver on generated boards.
+//!
+//! Usage: casspir-bench [beginner|intermediate|expert|WxH/MINES]... [--games N] [--seed N] [--strategy lowest-risk|win-probability]
+
+use casspir::solver::{self, BenchmarkConfig, GuessStrategy};
+use std::env;
+use std::process;
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: casspir-bench [beginner|intermediate|expert|WxH/MINES]... [--games N] [--seed N] [--strategy lowest-risk|win-probability]"
+    );
+    process::exit(2);
+}
+
+/// Parse a board given as `WxH/MINES`.
+fn parse_board(board: &str, games: u32) -> Option<BenchmarkConfig> {
+    let (size, mines) = board.split_once('/')?;
+    let (width, height) = size.split_once('x')?;
+    Some(BenchmarkConfig::new(
+        width.parse().ok()?,
+        height.parse().ok()?,
+        mines.parse().ok()?,
+        games,
+    ))
+}
+
+fn main() {
+    let mut boards: Vec<String> = Vec::new();
+    let mut games: u32 = 100;
+    let mut strategy = GuessStrategy::LowestRisk;
+    let mut seed: u64 = 0;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                games = match args.next().and_then(|games| games.parse().ok()) {
+                    Some(games) => games,
+                    None => usage(),
+                }
+            }
+            "--seed" => {
+                seed = match args.next().and_then(|seed| seed.parse().ok()) {
+                    Some(seed) => seed,
+                    None => usage(),
+                }
+            }
+            "--strategy" => {
+                strategy = match args.next().as_deref() {
+                    Some("lowest-risk") => GuessStrategy::LowestRisk,
+                    Some("win-probability") => GuessStrategy::WinProbability,
+                    _ => usage(),
+                }
+            }
+            "--help" | "-h" => usage(),
+            _ => boards.push(arg),
+        }
+    }
+    if boards.is_empty() {
+        boards = vec!["beginner".into(), "intermediate".into(), "expert".into()];
+    }
+
+    for board in boards {
+        let mut config = match board.as_str() {
+            "beginner" => BenchmarkConfig::beginner(games),
+            "intermediate" => BenchmarkConfig::intermediate(games),
+            "expert" => BenchmarkConfig::expert(games),
+            _ => match parse_board(&board, games) {
+                Some(config) => config,
+                None => usage(),
+            },
+        };
+        config.strategy = strategy;
+        config.seed = seed;
+        println!("{}", solver::benchmark(config));
+    }
+}
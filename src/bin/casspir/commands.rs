@@ -0,0 +1,272 @@
+//! The commands for working with boards outside of a game.
+
+use super::format::{self, Format};
+use super::{parse_board, Error};
+use casspir::map::{self, Map, Status};
+use casspir::point::Point;
+use casspir::render::{heatmap, RenderOptions};
+use casspir::solver::{self, GuessStrategy, Move, MoveType};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+
+/// Parse a position given as `X,Y`, which must be on a board of the given size.
+fn parse_click(click: &str, width: u16, height: u16) -> Result<Point, Error> {
+    let (x, y) = click.split_once(',').ok_or(Error::Usage)?;
+    let click = Point {
+        x: x.trim().parse().map_err(|_| Error::Usage)?,
+        y: y.trim().parse().map_err(|_| Error::Usage)?,
+    };
+    if click.x >= width || click.y >= height {
+        return Err(Error::Input(format!(
+            "{},{} is off the board",
+            click.x, click.y
+        )));
+    }
+    Ok(click)
+}
+
+fn parse_format(format: Option<String>) -> Result<Format, Error> {
+    format
+        .as_deref()
+        .and_then(Format::parse)
+        .ok_or(Error::Usage)
+}
+
+fn parse_seed(seed: Option<String>) -> Result<StdRng, Error> {
+    let seed: u64 = seed.and_then(|s| s.parse().ok()).ok_or(Error::Usage)?;
+    Ok(StdRng::seed_from_u64(seed))
+}
+
+/// Read a board from the file at `path`, or from stdin if there's no path or it is `-`.
+fn read_board(path: Option<String>) -> Result<(Map, Format), Error> {
+    let mut input = String::new();
+    match path.as_deref() {
+        None | Some("-") => {
+            io::stdin().read_to_string(&mut input)?;
+        }
+        Some(path) => input = fs::read_to_string(path)?,
+    }
+    format::read_board(&input).map_err(Error::Input)
+}
+
+/// Find where to start on a board, either the `click` given or an opening if nothing is flipped yet.
+fn first_click(map: &Map, click: Option<String>) -> Result<Option<Point>, Error> {
+    match click {
+        Some(click) => Ok(Some(parse_click(
+            &click,
+            map.get_width(),
+            map.get_height(),
+        )?)),
+        None if map.get_tiles_flipped() == 0 => {
+            Ok(Some(solver::find_opening(map).ok_or_else(|| {
+                Error::Input("the board has no opening to start from, pass --click".into())
+            })?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Generate a board and print it, with the first click flipped.
+pub fn generate(args: Vec<String>) -> Result<(), Error> {
+    let mut board: Option<String> = None;
+    let mut seed: Option<String> = None;
+    let mut click: Option<String> = None;
+    let mut no_guess: bool = false;
+    let mut output = Format::Text;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(args.next().ok_or(Error::Usage)?),
+            "--click" => click = Some(args.next().ok_or(Error::Usage)?),
+            "--no-guess" => no_guess = true,
+            "--format" => output = parse_format(args.next())?,
+            _ if board.is_none() && !arg.starts_with('-') => board = Some(arg),
+            _ => return Err(Error::Usage),
+        }
+    }
+
+    let (width, height, mines) = match board {
+        Some(board) => parse_board(&board).ok_or(Error::Usage)?,
+        None => (9, 9, 10),
+    };
+    let click: Point = match click {
+        Some(click) => parse_click(&click, width, height)?,
+        None => Point {
+            x: width / 2,
+            y: height / 2,
+        },
+    };
+    let mut rng: StdRng = match seed {
+        Some(_) => parse_seed(seed)?,
+        None => StdRng::from_entropy(),
+    };
+
+    let mut map = if no_guess {
+        map::generate_no_guess_map(width, height, mines, click.clone(), &mut rng).ok_or_else(
+            || Error::NoResult("couldn't find a board that can be solved without guessing".into()),
+        )?
+    } else {
+        map::generate_map_with_total_and_rng(width, height, mines, click.clone(), &mut rng)
+    };
+    map.flip(&click);
+    format::write_board(&mut io::stdout(), &map, output)?;
+    Ok(())
+}
+
+/// Solve a board and print the moves, starting with the first click if it was needed.
+pub fn solve(args: Vec<String>) -> Result<(), Error> {
+    let mut path: Option<String> = None;
+    let mut seed: Option<String> = None;
+    let mut click: Option<String> = None;
+    let mut strategy = GuessStrategy::LowestRisk;
+    let mut output = Format::Text;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(args.next().ok_or(Error::Usage)?),
+            "--click" => click = Some(args.next().ok_or(Error::Usage)?),
+            "--strategy" => {
+                strategy = match args.next().as_deref() {
+                    Some("lowest-risk") => GuessStrategy::LowestRisk,
+                    Some("win-probability") => GuessStrategy::WinProbability,
+                    _ => return Err(Error::Usage),
+                }
+            }
+            "--format" => output = parse_format(args.next())?,
+            _ if path.is_none() && (arg == "-" || !arg.starts_with('-')) => path = Some(arg),
+            _ => return Err(Error::Usage),
+        }
+    }
+    if output == Format::View {
+        return Err(Error::Usage);
+    }
+
+    let (mut map, _) = read_board(path)?;
+    let mut moves: VecDeque<Move> = VecDeque::new();
+    if let Some(position) = first_click(&map, click)? {
+        map.flip(&position);
+        moves.push_back(Move {
+            position,
+            move_type: MoveType::Flip,
+        });
+    }
+    let mut solution = match seed {
+        Some(_) => solver::solve_with_rng(&map, strategy, &mut parse_seed(seed)?),
+        None => solver::solve_with_strategy(&map, strategy),
+    };
+    map.apply_moves(&solution);
+    moves.append(&mut solution);
+
+    format::write_moves(&mut io::stdout(), &moves, output)?;
+    match map.get_status() {
+        Status::Complete => Ok(()),
+        _ => Err(Error::NoResult("the solver hit a mine".into())),
+    }
+}
+
+/// Print the 3BV, guesses and difficulty of a board.
+pub fn rate(args: Vec<String>) -> Result<(), Error> {
+    let mut path: Option<String> = None;
+    let mut output = Format::Text;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => output = parse_format(args.next())?,
+            _ if path.is_none() && (arg == "-" || !arg.starts_with('-')) => path = Some(arg),
+            _ => return Err(Error::Usage),
+        }
+    }
+
+    let (map, _) = read_board(path)?;
+    let rating = solver::rate(&map);
+    let mut stdout = io::stdout();
+    match output {
+        Format::Text => writeln!(stdout, "{}", rating)?,
+        Format::Json => writeln!(
+            stdout,
+            "{}",
+            json!({
+                "3bv": rating.three_bv,
+                "guesses": rating.guesses,
+                "difficulty": rating.difficulty,
+            })
+        )?,
+        Format::View => return Err(Error::Usage),
+    }
+    Ok(())
+}
+
+/// Print the chance of a mine on each hidden tile, as the solver sees it.
+pub fn heatmap(args: Vec<String>) -> Result<(), Error> {
+    let mut path: Option<String> = None;
+    let mut click: Option<String> = None;
+    let mut plain: bool = false;
+    let mut output = Format::Text;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--click" => click = Some(args.next().ok_or(Error::Usage)?),
+            "--plain" => plain = true,
+            "--format" => output = parse_format(args.next())?,
+            _ if path.is_none() && (arg == "-" || !arg.starts_with('-')) => path = Some(arg),
+            _ => return Err(Error::Usage),
+        }
+    }
+
+    let (mut map, _) = read_board(path)?;
+    if let Some(position) = first_click(&map, click)? {
+        map.flip(&position);
+    }
+    let mut stdout = io::stdout();
+    match output {
+        Format::Text => {
+            let options = if plain || !stdout.is_terminal() {
+                RenderOptions::plain()
+            } else {
+                RenderOptions::terminal()
+            };
+            heatmap::render_heatmap(&map, &mut stdout, &options)?;
+        }
+        Format::Json => {
+            let probabilities = solver::group_probabilities(&map);
+            let rows: Value = probabilities
+                .chunks(map.get_width() as usize)
+                .map(|row| row.iter().map(|p| json!(p)).collect::<Value>())
+                .collect();
+            writeln!(stdout, "{}", rows)?;
+        }
+        Format::View => return Err(Error::Usage),
+    }
+    Ok(())
+}
+
+/// Print a board in another format, JSON for text and text for JSON unless told otherwise.
+pub fn convert(args: Vec<String>) -> Result<(), Error> {
+    let mut path: Option<String> = None;
+    let mut output: Option<Format> = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => output = Some(parse_format(args.next())?),
+            _ if path.is_none() && (arg == "-" || !arg.starts_with('-')) => path = Some(arg),
+            _ => return Err(Error::Usage),
+        }
+    }
+
+    let (map, input) = read_board(path)?;
+    let output: Format = output.unwrap_or(match input {
+        Format::Json => Format::Text,
+        _ => Format::Json,
+    });
+    format::write_board(&mut io::stdout(), &map, output)?;
+    Ok(())
+}
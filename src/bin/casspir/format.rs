@@ -0,0 +1,519 @@
+//! Reading and writing boards as text or JSON.
+//!
+//! Text boards are written by `Map::print` with every tile revealed, so `*` is a mine and a digit is safe.
+//! Once tiles are flipped or flagged, the board as a player sees it follows after a blank line.
+//! Flags may only be placed on mines, as the board already says where the mines are.
+//! Only square boards with bounded edges and a single mine per tile can be written as text.
+//!
+//! JSON boards record the same state:
+//! `{"width":3,"height":1,"mines":[[0,0]],"flipped":[[2,0]],"flags":[]}`.
+//! A mine may be given as `[x, y, count]` when a tile holds more than one or a negative mine,
+//! along with the optional `"capacity"`, `"topology": "toroidal"` and `"missing"` tiles.
+
+use super::Error;
+use casspir::grid::Square;
+use casspir::map::{self, Layout, Map, Tile};
+use casspir::point::{self, Point, Topology};
+use casspir::solver::{Move, MoveType};
+use serde_json::{json, Map as Object, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// How a board is written.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Format {
+    /// Every tile revealed as printed by `Map::print`, then the player's view if anything is flipped or flagged.
+    Text,
+    /// The mines along with the flipped and flagged tiles and the shape of the board.
+    Json,
+    /// The board as a player sees it, which can't be read back.
+    View,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Option<Format> {
+        match format {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "view" => Some(Format::View),
+            _ => None,
+        }
+    }
+}
+
+/// Read a board in either format, telling them apart by the opening brace of JSON.
+pub fn read_board(input: &str) -> Result<(Map, Format), String> {
+    if input.trim_start().starts_with('{') {
+        Ok((read_json(input)?, Format::Json))
+    } else {
+        Ok((read_text(input)?, Format::Text))
+    }
+}
+
+fn read_text(input: &str) -> Result<Map, String> {
+    // Blocks of rows are separated by blank lines.
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+    for row in input.lines().map(|row| row.trim_end()) {
+        if !row.is_empty() {
+            block.push(row);
+        } else if !block.is_empty() {
+            blocks.push(std::mem::take(&mut block));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    let (rows, view) = match blocks.as_slice() {
+        [rows] => (rows, None),
+        [rows, view] => (rows, Some(view)),
+        _ => return Err("expected the revealed board, optionally followed by the view".into()),
+    };
+
+    let width: usize = rows.first().map_or(0, |row| row.chars().count());
+    if width == 0 || width > u16::MAX as usize || rows.len() > u16::MAX as usize {
+        return Err("the board is empty or too large".into());
+    }
+    let check_rows = |rows: &[&str]| -> Result<(), String> {
+        if rows.len() != blocks[0].len() {
+            return Err(format!(
+                "the view has {} rows, expected {}",
+                rows.len(),
+                blocks[0].len()
+            ));
+        }
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!(
+                    "row {} has {} tiles, expected {}",
+                    y,
+                    row.chars().count(),
+                    width
+                ));
+            }
+        }
+        Ok(())
+    };
+    check_rows(rows)?;
+
+    let mut mines: HashSet<Point> = HashSet::new();
+    let mut values: Vec<(Point, i16)> = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, symbol) in row.chars().enumerate() {
+            let position = Point {
+                x: x as u16,
+                y: y as u16,
+            };
+            match symbol {
+                '*' => {
+                    mines.insert(position);
+                }
+                '0'..='8' => values.push((position, symbol as i16 - '0' as i16)),
+                _ => return Err(format!("unexpected '{}' at {},{}", symbol, x, y)),
+            }
+        }
+    }
+
+    let mut map = map::generate_map_with_mines(width as u16, rows.len() as u16, mines);
+    for (position, value) in values {
+        if map.get_tile(position.to_index(map.get_width())).value != value {
+            return Err(format!(
+                "the {} at {},{} doesn't match the mines around it",
+                value, position.x, position.y
+            ));
+        }
+    }
+
+    let view = match view {
+        Some(view) => view,
+        None => return Ok(map),
+    };
+    check_rows(view)?;
+    let mut flags: Vec<Point> = Vec::new();
+    for (y, (row, revealed)) in view.iter().zip(rows.iter()).enumerate() {
+        for (x, (symbol, answer)) in row.chars().zip(revealed.chars()).enumerate() {
+            let position = Point {
+                x: x as u16,
+                y: y as u16,
+            };
+            match symbol {
+                '#' => {}
+                '^' if answer == '*' => flags.push(position),
+                '^' => return Err(format!("the flag at {},{} isn't on a mine", x, y)),
+                _ if symbol == answer => {
+                    map.reveal(&position);
+                }
+                _ => {
+                    return Err(format!(
+                        "the view has '{}' at {},{} where the board has '{}'",
+                        symbol, x, y, answer
+                    ))
+                }
+            }
+        }
+    }
+    for position in flags {
+        map.flag(&position);
+    }
+    Ok(map)
+}
+
+/// Read an `x` and `y` coordinate listed in the `key` of a JSON board, which must be on the board.
+fn read_point(
+    position: &Value,
+    (x, y): (&Value, &Value),
+    key: &str,
+    width: u16,
+    height: u16,
+) -> Result<Point, String> {
+    match (x.as_u64(), y.as_u64()) {
+        (Some(x), Some(y)) if x < width as u64 && y < height as u64 => Ok(Point {
+            x: x as u16,
+            y: y as u16,
+        }),
+        _ => Err(format!("{} in \"{}\" is off the board", position, key)),
+    }
+}
+
+/// Read a list of `[x, y]` positions from the `key` of a JSON board, which may be missing.
+fn read_points(board: &Value, key: &str, width: u16, height: u16) -> Result<Vec<Point>, String> {
+    let points = match board.get(key) {
+        Some(Value::Array(points)) => points,
+        None => return Ok(Vec::new()),
+        _ => return Err(format!("\"{}\" must be a list of [x, y] positions", key)),
+    };
+    points
+        .iter()
+        .map(|position| match position.as_array().map(|p| p.as_slice()) {
+            Some([x, y]) => read_point(position, (x, y), key, width, height),
+            _ => Err(format!(
+                "{} in \"{}\" isn't an [x, y] position",
+                position, key
+            )),
+        })
+        .collect()
+}
+
+/// Read the mines or flags listed in the `key` of a JSON board as `[x, y]` or `[x, y, count]`,
+/// leaving out tiles with a count of 0.
+fn read_counts(
+    board: &Value,
+    key: &str,
+    width: u16,
+    height: u16,
+) -> Result<HashMap<Point, i8>, String> {
+    let mines = match board.get(key) {
+        Some(Value::Array(mines)) => mines,
+        None => return Ok(HashMap::new()),
+        _ => return Err(format!("\"{}\" must be a list of [x, y] positions", key)),
+    };
+    let mut counts: HashMap<Point, i8> = HashMap::new();
+    for mine in mines {
+        let (position, count) = match mine.as_array().map(|m| m.as_slice()) {
+            Some([x, y]) => (read_point(mine, (x, y), key, width, height)?, 1),
+            Some([x, y, count]) => match count.as_i64() {
+                Some(count) if count.unsigned_abs() <= i8::MAX as u64 => {
+                    (read_point(mine, (x, y), key, width, height)?, count as i8)
+                }
+                _ => return Err(format!("{} in \"{}\" has a bad count", mine, key)),
+            },
+            _ => return Err(format!("{} in \"{}\" isn't an [x, y] position", mine, key)),
+        };
+        if count != 0 {
+            counts.insert(position, count);
+        }
+    }
+    Ok(counts)
+}
+
+fn read_json(input: &str) -> Result<Map, String> {
+    let board: Value = serde_json::from_str(input).map_err(|error| error.to_string())?;
+    let dimension = |key: &str| -> Result<u16, String> {
+        match board.get(key).and_then(Value::as_u64) {
+            Some(size) if size > 0 && size <= u16::MAX as u64 => Ok(size as u16),
+            _ => Err(format!("\"{}\" must be a positive whole number", key)),
+        }
+    };
+    let (width, height) = (dimension("width")?, dimension("height")?);
+
+    let mut layout = Layout::new(width, height);
+    layout.topology = match board.get("topology").map(|t| t.as_str()) {
+        None | Some(Some("bounded")) => Topology::Bounded,
+        Some(Some("toroidal")) => Topology::Toroidal,
+        _ => return Err("\"topology\" must be \"bounded\" or \"toroidal\"".into()),
+    };
+    for position in read_points(&board, "missing", width, height)? {
+        layout.mask[position.to_index(width)] = false;
+    }
+    let capacity: u8 = match board.get("capacity") {
+        None => 1,
+        Some(capacity) => match capacity.as_u64() {
+            Some(capacity) if capacity > 0 && capacity <= i8::MAX as u64 => capacity as u8,
+            _ => return Err("\"capacity\" must be a positive whole number".into()),
+        },
+    };
+
+    let mines: HashMap<Point, i8> = read_counts(&board, "mines", width, height)?;
+    for (position, count) in &mines {
+        if !layout.mask[position.to_index(width)] {
+            return Err(format!(
+                "the mine at {},{} is missing",
+                position.x, position.y
+            ));
+        }
+        if count.unsigned_abs() > capacity {
+            return Err(format!(
+                "the tile at {},{} holds more mines than its capacity",
+                position.x, position.y
+            ));
+        }
+    }
+    let mut map = map::generate_map_with_layout_and_mine_counts(layout, mines, capacity);
+    for position in read_points(&board, "flipped", width, height)? {
+        map.reveal(&position);
+    }
+    for (position, count) in read_counts(&board, "flags", width, height)? {
+        let tile: &Tile = map.get_tile(position.to_index(width));
+        if count.signum() != tile.mines.signum() || count.abs() > tile.mines.abs() {
+            return Err(format!(
+                "the flags at {},{} don't match the mines there",
+                position.x, position.y
+            ));
+        }
+        if tile.is_flagged() {
+            continue;
+        }
+        for _ in 0..count.unsigned_abs() {
+            if count > 0 {
+                map.flag(&position);
+            } else {
+                map.flag_negative(&position);
+            }
+        }
+    }
+    Ok(map)
+}
+
+/// List the positions of the tiles matching the `filter` as `[x, y]` pairs.
+fn points_json(map: &Map, filter: impl Fn(usize) -> bool) -> Value {
+    (0..map.get_tiles().len())
+        .filter(|index| filter(*index))
+        .map(|index| {
+            let position = point::from_index(index, map.get_width());
+            json!([position.x, position.y])
+        })
+        .collect()
+}
+
+/// List the tiles with a nonzero count as `[x, y]`, or `[x, y, count]` unless the count is 1.
+fn counts_json(map: &Map, count: impl Fn(&Tile) -> i8) -> Value {
+    (0..map.get_tiles().len())
+        .filter(|index| count(map.get_tile(*index)) != 0)
+        .map(|index| {
+            let position = point::from_index(index, map.get_width());
+            match count(map.get_tile(index)) {
+                1 => json!([position.x, position.y]),
+                count => json!([position.x, position.y, count]),
+            }
+        })
+        .collect()
+}
+
+fn is_square(map: &Map) -> bool {
+    map.get_grid().eq_grid(&Square)
+}
+
+/// Checks if the board can be written as text, which only holds square boards
+/// with bounded edges, no missing tiles and a single positive mine per tile.
+fn fits_text(map: &Map) -> bool {
+    is_square(map)
+        && map.get_topology() == Topology::Bounded
+        && map.get_mask().iter().all(|exists| *exists)
+        && map.get_tile_capacity() == 1
+        && !map.has_negative_mines()
+}
+
+/// Write the board in the given `format`.
+/// Boards that the format can't hold are refused as bad input, as is any board
+/// on a grid other than squares, which neither format records.
+pub fn write_board(out: &mut dyn Write, map: &Map, format: Format) -> Result<(), Error> {
+    match format {
+        Format::Text => {
+            if !fits_text(map) {
+                return Err(Error::Input(
+                    "this board can't be written as text, use --to json".into(),
+                ));
+            }
+            map.print(out, true)?;
+            let tiles = map.get_tiles();
+            if tiles.iter().any(|tile| tile.flipped || tile.is_flagged()) {
+                map.print(out, false)?;
+            }
+        }
+        Format::View => map.print(out, false)?,
+        Format::Json => {
+            if !is_square(map) {
+                return Err(Error::Input(
+                    "only boards of square tiles can be written as JSON".into(),
+                ));
+            }
+            let mut board: Object<String, Value> = Object::new();
+            board.insert("width".into(), json!(map.get_width()));
+            board.insert("height".into(), json!(map.get_height()));
+            board.insert("mines".into(), counts_json(map, |tile| tile.mines));
+            board.insert(
+                "flipped".into(),
+                points_json(map, |i| map.get_tile(i).flipped),
+            );
+            board.insert("flags".into(), counts_json(map, |tile| tile.flags));
+            if map.get_tile_capacity() > 1 {
+                board.insert("capacity".into(), json!(map.get_tile_capacity()));
+            }
+            if map.get_topology() == Topology::Toroidal {
+                board.insert("topology".into(), json!("toroidal"));
+            }
+            if !map.get_mask().iter().all(|exists| *exists) {
+                board.insert("missing".into(), points_json(map, |i| !map.tile_exists(i)));
+            }
+            writeln!(out, "{}", Value::Object(board))?;
+        }
+    }
+    Ok(())
+}
+
+fn move_name(move_type: &MoveType) -> &'static str {
+    match move_type {
+        MoveType::Flip => "Flip",
+        MoveType::Flag => "Flag",
+        MoveType::NegativeFlag => "NegativeFlag",
+    }
+}
+
+/// Write moves one per line as `Flip (x,y)`, or as a JSON list.
+pub fn write_moves<'a>(
+    out: &mut dyn Write,
+    moves: impl IntoIterator<Item = &'a Move>,
+    format: Format,
+) -> io::Result<()> {
+    if format == Format::Json {
+        let moves: Value = moves
+            .into_iter()
+            .map(|play| {
+                json!({
+                    "move": move_name(&play.move_type).to_lowercase(),
+                    "x": play.position.x,
+                    "y": play.position.y,
+                })
+            })
+            .collect();
+        return writeln!(out, "{}", moves);
+    }
+    for play in moves {
+        writeln!(
+            out,
+            "{} ({},{})",
+            move_name(&play.move_type),
+            play.position.x,
+            play.position.y
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_string(map: &Map, format: Format) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        write_board(&mut out, map, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let click = Point { x: 4, y: 4 };
+        let mut map = map::generate_map_with_total(9, 9, 10, click.clone());
+        map.flip(&click);
+        let mine = (0..81).find(|i| map.get_tile(*i).is_mine()).unwrap();
+        map.flag(&point::from_index(mine, 9));
+
+        // JSON keeps the whole state of the board.
+        let (read, format) = read_board(&write_string(&map, Format::Json)).unwrap();
+        assert_eq!(Format::Json, format);
+        assert!(read == map);
+
+        // Text keeps it too, with the player's view after the revealed board.
+        let (read, format) = read_board(&write_string(&map, Format::Text)).unwrap();
+        assert_eq!(Format::Text, format);
+        assert!(read == map);
+
+        // A board nobody has played stays a single block.
+        let fresh = map::generate_map_with_total(9, 9, 10, click);
+        let text = write_string(&fresh, Format::Text);
+        assert_eq!(10, text.lines().count());
+        assert!(read_board(&text).unwrap().0 == fresh);
+    }
+
+    #[test]
+    fn test_board_shapes() {
+        let mut layout = Layout::new(4, 3);
+        layout.topology = Topology::Toroidal;
+        layout.mask[11] = false;
+        let mines: HashMap<Point, i8> = [(Point { x: 0, y: 0 }, 2), (Point { x: 2, y: 1 }, -1)]
+            .iter()
+            .cloned()
+            .collect();
+        let mut map = map::generate_map_with_layout_and_mine_counts(layout, mines, 2);
+        map.reveal(&Point { x: 1, y: 2 });
+        map.flag(&Point { x: 0, y: 0 });
+        map.flag(&Point { x: 0, y: 0 });
+        map.flag_negative(&Point { x: 2, y: 1 });
+
+        let json = write_string(&map, Format::Json);
+        let (read, _) = read_board(&json).unwrap();
+        assert!(read == map);
+        assert_eq!(json, write_string(&read, Format::Json));
+
+        // Text can't hold any of this, so it is refused rather than written without it.
+        let mut out: Vec<u8> = Vec::new();
+        assert!(matches!(
+            write_board(&mut out, &map, Format::Text),
+            Err(Error::Input(_))
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_bad_boards() {
+        assert!(read_board("").is_err());
+        assert!(read_board("\n*1\n110\n").is_err());
+        assert!(read_board("\n*2\n11\n").is_err());
+        assert!(read_board("\n*#\n11\n").is_err());
+        assert!(read_board("{\"width\": 2}").is_err());
+        assert!(read_board("{\"width\": 2, \"height\": 2, \"mines\": [[2, 0]]}").is_err());
+        assert!(read_board("\n*1\n11\n").is_ok());
+        assert!(read_board("\n*1\n11\n\n#1\n1#\n").is_ok());
+        assert!(read_board("\n*1\n11\n\n#2\n1#\n").is_err());
+        assert!(read_board("\n*1\n11\n\n#1\n").is_err());
+        assert!(read_board("{\"width\": 2, \"height\": 1, \"mines\": [[0, 0, 2]]}").is_err());
+        assert!(read_board("{\"width\": 2, \"height\": 1, \"topology\": \"flat\"}").is_err());
+    }
+
+    #[test]
+    fn test_wrong_flags() {
+        // Flags have to be on mines, or the solver would plan around mines that aren't there.
+        assert!(read_board("\n*1\n11\n\n^1\n1#\n").is_ok());
+        assert!(read_board("\n*1\n11\n\n#1\n1^\n").is_err());
+        let json = |flags: &str| {
+            format!(
+                "{{\"width\":4,\"height\":4,\"mines\":[[0,0]],\"flags\":{}}}",
+                flags
+            )
+        };
+        assert!(read_board(&json("[[0,0]]")).is_ok());
+        assert!(read_board(&json("[[3,3]]")).is_err());
+        assert!(read_board(&json("[[0,0,-1]]")).is_err());
+        assert!(read_board(&json("[[0,0,2]]")).is_err());
+    }
+}
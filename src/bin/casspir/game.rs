@@ -0,0 +1,298 @@
+//! The interactive game played in the terminal.
+
+use casspir::map::{self, Map, Status};
+use casspir::point::Point;
+use casspir::render::{self, RenderOptions};
+use casspir::solver::{self, GuessStrategy, MoveType};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::{self, Write};
+use std::panic;
+use std::time::{Duration, Instant};
+
+const HELP: &str = "arrows/hjkl move  space flip  f flag  c chord  ? hint  n new game  q quit";
+
+/// A game in progress. The map is only generated on the first flip, so that it is always safe.
+struct Game {
+    width: u16,
+    height: u16,
+    mines: u32,
+    map: Option<Map>,
+    cursor: Point,
+    started: Option<Instant>,
+    finished: Option<Duration>,
+    message: String,
+}
+
+impl Game {
+    fn new(width: u16, height: u16, mines: u32) -> Game {
+        Game {
+            width,
+            height,
+            mines,
+            map: None,
+            cursor: Point {
+                x: width / 2,
+                y: height / 2,
+            },
+            started: None,
+            finished: None,
+            message: String::new(),
+        }
+    }
+
+    fn get_status(&self) -> Status {
+        match &self.map {
+            Some(map) => map.get_status().clone(),
+            None => Status::InProgress,
+        }
+    }
+
+    fn get_mines_remaining(&self) -> u32 {
+        match &self.map {
+            Some(map) => map.get_mines_remaining(),
+            None => self.mines,
+        }
+    }
+
+    /// The number of whole seconds played so far.
+    fn get_seconds(&self) -> u64 {
+        match (self.finished, self.started) {
+            (Some(duration), _) => duration.as_secs(),
+            (None, Some(started)) => started.elapsed().as_secs(),
+            (None, None) => 0,
+        }
+    }
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        self.cursor.x = (self.cursor.x as i32 + dx).clamp(0, self.width as i32 - 1) as u16;
+        self.cursor.y = (self.cursor.y as i32 + dy).clamp(0, self.height as i32 - 1) as u16;
+    }
+
+    /// Flip the tile under the cursor, or chord it if it's already flipped.
+    fn flip(&mut self) {
+        let cursor = self.cursor.clone();
+        let (width, height, mines) = (self.width, self.height, self.mines);
+        let map = self.map.get_or_insert_with(|| {
+            map::generate_map_with_total(width, height, mines, cursor.clone())
+        });
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+        map.flip(&cursor);
+        self.message.clear();
+        self.check_finished();
+    }
+
+    /// Flip the neighbours of the flipped tile under the cursor if it has as many flags as its value.
+    fn chord(&mut self) {
+        if let Some(map) = &self.map {
+            if map.get_tile(self.cursor.to_index(self.width)).flipped {
+                self.flip();
+            }
+        }
+    }
+
+    fn flag(&mut self) {
+        if let Some(map) = self.map.as_mut() {
+            map.flag(&self.cursor);
+        }
+    }
+
+    /// Ask the solver for the next move and point the cursor at it.
+    /// The solver ignores the flags placed so far, as they might be wrong.
+    fn hint(&mut self) {
+        let map = match &self.map {
+            Some(map) if *map.get_status() == Status::InProgress => map,
+            _ => {
+                self.message = "The first flip is always safe.".into();
+                return;
+            }
+        };
+
+        let unflagged = map.without_flags();
+        let moves = solver::solve_with_strategy(&unflagged, GuessStrategy::WinProbability);
+        // Skip flags the player has already placed.
+        let hint = match moves.iter().find(|play| {
+            play.move_type == MoveType::Flip
+                || !map
+                    .get_tile(play.position.to_index(self.width))
+                    .is_flagged()
+        }) {
+            Some(hint) => hint.clone(),
+            None => return,
+        };
+        let probability: f64 = match solver::mine_probabilities(&unflagged) {
+            Some(probabilities) => probabilities[hint.position.to_index(self.width)],
+            None => return,
+        };
+        self.message = match hint.move_type {
+            MoveType::Flip if probability == 0.0 => {
+                format!("Hint: {},{} is safe.", hint.position.x, hint.position.y)
+            }
+            MoveType::Flip => format!(
+                "Hint: nothing is certain, {},{} has a {:.0}% chance of a mine.",
+                hint.position.x,
+                hint.position.y,
+                probability * 100.0
+            ),
+            _ => format!("Hint: {},{} is a mine.", hint.position.x, hint.position.y),
+        };
+        self.cursor = hint.position;
+    }
+
+    fn check_finished(&mut self) {
+        let status = self.get_status();
+        if status == Status::InProgress || self.finished.is_some() {
+            return;
+        }
+        self.finished = Some(
+            self.started
+                .map_or(Duration::ZERO, |started| started.elapsed()),
+        );
+        self.message = if status == Status::Complete {
+            format!(
+                "You won in {} seconds! Press n for a new game.",
+                self.get_seconds()
+            )
+        } else {
+            "Boom! You hit a mine. Press n for a new game.".into()
+        };
+    }
+}
+
+/// Convert a colour used by the renderer to the matching terminal colour.
+fn terminal_color(color: Option<render::Color>) -> Color {
+    match color {
+        Some(render::Color::Blue) => Color::Blue,
+        Some(render::Color::Green) => Color::Green,
+        Some(render::Color::Red) => Color::Red,
+        Some(render::Color::DarkBlue) => Color::DarkBlue,
+        Some(render::Color::DarkRed) => Color::DarkRed,
+        Some(render::Color::Cyan) => Color::Cyan,
+        Some(render::Color::Magenta) => Color::Magenta,
+        Some(render::Color::Grey) => Color::Grey,
+        Some(render::Color::DarkGrey) => Color::DarkGrey,
+        Some(render::Color::Yellow) => Color::Yellow,
+        Some(render::Color::White) => Color::White,
+        None => Color::Reset,
+    }
+}
+
+fn draw(out: &mut impl Write, game: &Game) -> io::Result<()> {
+    queue!(
+        out,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(terminal::ClearType::All),
+        Print(format!(
+            "Mines: {:<4} Time: {}",
+            game.get_mines_remaining(),
+            game.get_seconds()
+        ))
+    )?;
+
+    let options = RenderOptions::terminal();
+    for y in 0..game.height {
+        queue!(out, cursor::MoveTo(0, y + 2))?;
+        for x in 0..game.width {
+            let glyph = match &game.map {
+                Some(map) => render::tile_glyph(map, Point { x, y }.to_index(game.width), &options),
+                None => render::Glyph {
+                    symbol: '■',
+                    color: Some(render::Color::DarkGrey),
+                    background: None,
+                },
+            };
+            let selected: bool = game.cursor == Point { x, y };
+            queue!(out, Print(' '))?;
+            if selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                out,
+                SetForegroundColor(terminal_color(glyph.color)),
+                SetBackgroundColor(terminal_color(glyph.background)),
+                Print(glyph.symbol),
+                ResetColor
+            )?;
+            if selected {
+                queue!(out, SetAttribute(Attribute::Reset))?;
+            }
+        }
+    }
+
+    queue!(
+        out,
+        cursor::MoveTo(0, game.height + 3),
+        Print(&game.message),
+        cursor::MoveTo(0, game.height + 4),
+        SetForegroundColor(Color::DarkGrey),
+        Print(HELP),
+        ResetColor
+    )?;
+    out.flush()
+}
+
+fn run(out: &mut impl Write, width: u16, height: u16, mines: u32) -> io::Result<()> {
+    let mut game = Game::new(width, height, mines);
+    loop {
+        draw(out, &game)?;
+
+        // Wake up regularly to keep the timer ticking.
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let code = match event::read()? {
+            Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) => code,
+            _ => continue,
+        };
+
+        let playing: bool = game.get_status() == Status::InProgress;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('n') => game = Game::new(width, height, mines),
+            KeyCode::Left | KeyCode::Char('h') => game.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => game.move_cursor(1, 0),
+            KeyCode::Up | KeyCode::Char('k') => game.move_cursor(0, -1),
+            KeyCode::Down | KeyCode::Char('j') => game.move_cursor(0, 1),
+            KeyCode::Char(' ') | KeyCode::Enter if playing => game.flip(),
+            KeyCode::Char('f') if playing => game.flag(),
+            KeyCode::Char('c') if playing => game.chord(),
+            KeyCode::Char('?') if playing => game.hint(),
+            _ => {}
+        }
+    }
+}
+
+/// Give the terminal back in a usable state, ignoring any errors.
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Play games on boards of the given size until the player quits.
+pub fn play(width: u16, height: u16, mines: u32) -> io::Result<()> {
+    // Restore the terminal before reporting a panic, so the message can be read.
+    let report = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        report(info);
+    }));
+
+    let mut stdout = io::stdout();
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide))
+        .and_then(|_| run(&mut stdout, width, height, mines));
+
+    restore_terminal();
+    // Go back to the default hook now the terminal is restored.
+    drop(panic::take_hook());
+    result
+}
@@ -0,0 +1,127 @@
+//! Play minesweeper in the terminal, or generate, solve, rate and convert boards.
+//!
+//! Run `casspir --help` for usage.
+
+mod commands;
+mod format;
+mod game;
+
+use std::env;
+use std::fmt;
+use std::io;
+use std::process;
+
+const USAGE: &str = "Usage:
+  casspir [play] [BOARD]
+  casspir generate [BOARD] [--seed N] [--click X,Y] [--no-guess] [--format text|json|view]
+  casspir solve [FILE] [--click X,Y] [--seed N] [--strategy lowest-risk|win-probability] [--format text|json]
+  casspir rate [FILE] [--format text|json]
+  casspir heatmap [FILE] [--click X,Y] [--plain] [--format text|json]
+  casspir convert [FILE] [--to text|json|view]
+
+BOARD is beginner, intermediate, expert or WxH/MINES, and defaults to beginner.
+FILE is a board in text or JSON, read from stdin if it's missing or -.
+Text can't hold missing tiles, wrapped edges, negative mines or several mines per tile, use JSON.";
+
+/// Why a command failed, each with its own exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// The arguments couldn't be understood.
+    Usage,
+    /// The board given was invalid.
+    Input(String),
+    Io(io::Error),
+    /// There was nothing to show, such as when the solver hit a mine.
+    NoResult(String),
+}
+
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Error::Input(_) | Error::Io(_) => 1,
+            Error::Usage => 2,
+            Error::NoResult(_) => 3,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage => write!(f, "{}", USAGE),
+            Error::Input(message) | Error::NoResult(message) => write!(f, "casspir: {}", message),
+            Error::Io(error) => write!(f, "casspir: {}", error),
+        }
+    }
+}
+
+/// Parse a board given as a preset name or as `WxH/MINES`.
+pub fn parse_board(board: &str) -> Option<(u16, u16, u32)> {
+    match board {
+        "beginner" => return Some((9, 9, 10)),
+        "intermediate" => return Some((16, 16, 40)),
+        "expert" => return Some((30, 16, 99)),
+        _ => {}
+    }
+    let (size, mines) = board.split_once('/')?;
+    let (width, height) = size.split_once('x')?;
+    let (width, height, mines): (u16, u16, u32) = (
+        width.parse().ok()?,
+        height.parse().ok()?,
+        mines.parse().ok()?,
+    );
+    // Leave room for the first click to be safe.
+    if width == 0 || height == 0 || mines >= width as u32 * height as u32 {
+        return None;
+    }
+    Some((width, height, mines))
+}
+
+fn play(args: Vec<String>) -> Result<(), Error> {
+    let (width, height, mines) = match args.as_slice() {
+        [] => (9, 9, 10),
+        [board] => parse_board(board).ok_or(Error::Usage)?,
+        _ => return Err(Error::Usage),
+    };
+    Ok(game::play(width, height, mines)?)
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let command: Option<String> = match args.first().map(|arg| arg.as_str()) {
+        Some(
+            "play" | "generate" | "solve" | "rate" | "heatmap" | "convert" | "--help" | "-h"
+            | "help",
+        ) => Some(args.remove(0)),
+        _ => None,
+    };
+
+    let result = match command.as_deref() {
+        None | Some("play") => play(args),
+        Some("generate") => commands::generate(args),
+        Some("solve") => commands::solve(args),
+        Some("rate") => commands::rate(args),
+        Some("heatmap") => commands::heatmap(args),
+        Some("convert") => commands::convert(args),
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+
+    match result {
+        // Stop quietly when output is piped into something that only reads part of it.
+        Err(Error::Io(error)) if error.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(error.exit_code());
+        }
+        Ok(()) => {}
+    }
+}
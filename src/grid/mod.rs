@@ -0,0 +1,583 @@
+//! This module contains the grids that define which tiles of a map are adjacent.
+
+use crate::point::{self, Point, Point3, Topology};
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::io::{self, Write};
+
+/// Describes how the tiles of a map are arranged.
+pub trait Grid: Debug + Send + Sync {
+    /// Get the points adjacent to `position` on a `width` by `height` grid.
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+    ) -> HashSet<Point>;
+
+    /// Call `visit` with each point adjacent to `position`, like `get_neighbours`
+    /// but without collecting them into a set. A point may be visited more than once
+    /// when small toroidal grids wrap an offset onto the same tile.
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        for neighbour in self.get_neighbours(position, width, height, topology) {
+            visit(neighbour);
+        }
+    }
+
+    /// If the grid can arrange a `width` by `height` map with the given `topology`.
+    fn supports(&self, _width: u16, _height: u16, _topology: Topology) -> bool {
+        true
+    }
+
+    /// Get the grid as `Any`, so it can be compared with grids of the same type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// If this grid describes the same arrangement as the `other` grid.
+    fn eq_grid(&self, other: &dyn Grid) -> bool;
+
+    /// Write whatever precedes the given `row` when printing a map.
+    fn write_row_start(&self, writer: &mut dyn Write, _row: u16) -> io::Result<()> {
+        writeln!(writer)
+    }
+
+    /// Write whatever separates two tiles in a row when printing a map.
+    fn write_tile_separator(&self, _writer: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl PartialEq for dyn Grid {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_grid(other)
+    }
+}
+
+/// Compare a `grid` with an `other` grid, which is only equal if it has the same type.
+fn eq_grid<T: PartialEq + 'static>(grid: &T, other: &dyn Grid) -> bool {
+    other
+        .as_any()
+        .downcast_ref::<T>()
+        .is_some_and(|other| grid == other)
+}
+
+/// A grid of square tiles, each touching up to 8 others.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Square;
+
+impl Grid for Square {
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+    ) -> HashSet<Point> {
+        point::get_neighbours_with_topology(position, width, height, topology)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_grid(&self, other: &dyn Grid) -> bool {
+        eq_grid(self, other)
+    }
+
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        point::neighbours(position, width, height, topology).for_each(visit);
+    }
+}
+
+/// A grid of square tiles where each tile touches the tiles at a fixed set of offsets,
+/// instead of the 8 surrounding it.
+///
+/// ```
+/// use casspir::grid::{Grid, Neighbourhood};
+/// use casspir::point::{Point, Topology};
+///
+/// let knight = Neighbourhood::knight();
+/// let neighbours = knight.get_neighbours(&Point { x: 0, y: 0 }, 8, 8, Topology::Bounded);
+/// assert_eq!(2, neighbours.len());
+/// assert!(neighbours.contains(&Point { x: 1, y: 2 }));
+/// assert!(neighbours.contains(&Point { x: 2, y: 1 }));
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct Neighbourhood {
+    /// The sorted `(dx, dy)` offsets of the adjacent tiles.
+    offsets: Vec<(i32, i32)>,
+}
+
+impl Neighbourhood {
+    /// Create a neighbourhood from a list of `(dx, dy)` `offsets`.
+    /// Adjacency is mutual, so the opposite of every offset is included too.
+    pub fn new(offsets: Vec<(i32, i32)>) -> Neighbourhood {
+        let mut symmetric: Vec<(i32, i32)> = offsets
+            .iter()
+            .flat_map(|(dx, dy)| vec![(*dx, *dy), (-dx, -dy)])
+            .filter(|offset| *offset != (0, 0))
+            .collect();
+        symmetric.sort_unstable();
+        symmetric.dedup();
+
+        Neighbourhood { offsets: symmetric }
+    }
+
+    /// Every tile within a Chebyshev distance of `radius`, a `radius` of 1 matches `Square`.
+    pub fn radius(radius: u16) -> Neighbourhood {
+        let radius = radius as i32;
+        Neighbourhood::new(
+            (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                .collect(),
+        )
+    }
+
+    /// Every tile within a Manhattan distance of `radius`, a `radius` of 1 only touches edges.
+    pub fn diamond(radius: u16) -> Neighbourhood {
+        let radius = radius as i32;
+        Neighbourhood::new(
+            (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                .filter(|(dx, dy)| dx.abs() + dy.abs() <= radius)
+                .collect(),
+        )
+    }
+
+    /// The 8 tiles a chess knight could move to.
+    pub fn knight() -> Neighbourhood {
+        Neighbourhood::new(vec![(1, 2), (2, 1), (2, -1), (1, -2)])
+    }
+
+    pub fn get_offsets(&self) -> &Vec<(i32, i32)> {
+        &self.offsets
+    }
+}
+
+impl Grid for Neighbourhood {
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+    ) -> HashSet<Point> {
+        let mut neighbours = HashSet::new();
+        self.for_each_neighbour(position, width, height, topology, &mut |neighbour| {
+            neighbours.insert(neighbour);
+        });
+        neighbours
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_grid(&self, other: &dyn Grid) -> bool {
+        eq_grid(self, other)
+    }
+
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        visit_offsets(position, &self.offsets, width, height, topology, visit);
+    }
+}
+
+/// Call `visit` with the point at each of the `offsets` from `position`,
+/// wrapping or skipping those past the edges according to the `topology`.
+fn visit_offsets(
+    position: &Point,
+    offsets: &[(i32, i32)],
+    width: u16,
+    height: u16,
+    topology: Topology,
+    visit: &mut dyn FnMut(Point),
+) {
+    for (dx, dy) in offsets.iter() {
+        let mut x: i32 = position.x as i32 + dx;
+        let mut y: i32 = position.y as i32 + dy;
+
+        if topology == Topology::Toroidal {
+            x = x.rem_euclid(width as i32);
+            y = y.rem_euclid(height as i32);
+        } else if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+            continue;
+        }
+
+        let neighbour = Point {
+            x: x as u16,
+            y: y as u16,
+        };
+        // On small puzzles an offset can wrap back onto the tile itself.
+        if neighbour != *position {
+            visit(neighbour);
+        }
+    }
+}
+
+/// A grid of hexagonal tiles, each touching up to 6 others.
+/// Tiles use offset coordinates where every odd row is shifted half a tile to the right.
+/// Toroidal hex grids must have an even height so that the rows line up when wrapping.
+///
+/// ```
+/// use casspir::grid::{Grid, Hex};
+/// use casspir::point::{Point, Topology};
+/// use std::collections::HashSet;
+///
+/// let expected: HashSet<Point> = [
+///     Point { x: 0, y: 2 },
+///     Point { x: 2, y: 2 },
+///     Point { x: 0, y: 1 },
+///     Point { x: 1, y: 1 },
+///     Point { x: 0, y: 3 },
+///     Point { x: 1, y: 3 },
+/// ].iter().cloned().collect();
+/// assert_eq!(Hex.get_neighbours(&Point { x: 1, y: 2 }, 5, 5, Topology::Bounded), expected);
+/// ```
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Hex;
+
+impl Grid for Hex {
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+    ) -> HashSet<Point> {
+        let mut neighbours = HashSet::new();
+        self.for_each_neighbour(position, width, height, topology, &mut |neighbour| {
+            neighbours.insert(neighbour);
+        });
+        neighbours
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_grid(&self, other: &dyn Grid) -> bool {
+        eq_grid(self, other)
+    }
+
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        width: u16,
+        height: u16,
+        topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        // Rows above and below reach one column further left on even rows and right on odd rows.
+        let shift: i32 = if position.y % 2 == 0 { -1 } else { 0 };
+        let offsets: [(i32, i32); 6] = [
+            (-1, 0),
+            (1, 0),
+            (shift, -1),
+            (shift + 1, -1),
+            (shift, 1),
+            (shift + 1, 1),
+        ];
+        visit_offsets(position, &offsets, width, height, topology, visit);
+    }
+
+    fn supports(&self, _width: u16, height: u16, topology: Topology) -> bool {
+        topology != Topology::Toroidal || height % 2 == 0
+    }
+
+    fn write_row_start(&self, writer: &mut dyn Write, row: u16) -> io::Result<()> {
+        writeln!(writer)?;
+        if row % 2 == 1 {
+            write!(writer, " ")?;
+        }
+        Ok(())
+    }
+
+    fn write_tile_separator(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write!(writer, " ")
+    }
+}
+
+/// A three dimensional grid of cubes, each touching up to 26 others.
+/// Layers of `height` rows are stacked vertically, see `Point3::to_point`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Cube {
+    /// The height of each layer.
+    pub height: u16,
+    /// The number of layers.
+    pub depth: u16,
+}
+
+impl Grid for Cube {
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        width: u16,
+        _height: u16,
+        topology: Topology,
+    ) -> HashSet<Point> {
+        let position = Point3::from_point(position, self.height);
+        point::get_neighbours_3d(&position, width, self.height, self.depth, topology)
+            .iter()
+            .map(|neighbour| neighbour.to_point(self.height))
+            .collect()
+    }
+
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        width: u16,
+        _height: u16,
+        topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        let position = Point3::from_point(position, self.height);
+        for neighbour in point::neighbours_3d(&position, width, self.height, self.depth, topology) {
+            visit(neighbour.to_point(self.height));
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_grid(&self, other: &dyn Grid) -> bool {
+        eq_grid(self, other)
+    }
+
+    fn write_row_start(&self, writer: &mut dyn Write, row: u16) -> io::Result<()> {
+        // Separate each layer with a blank line.
+        if row > 0 && row % self.height == 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer)
+    }
+}
+
+/// An arbitrary graph of tiles, where any tile may touch any other.
+/// Nodes are laid out along a single row, so node `n` is at `Point { x: n, y: 0 }`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Graph {
+    /// The sorted indices of the nodes adjacent to each node.
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    /// Create a graph from an `adjacency` list, one list of adjacent nodes per node.
+    /// Edges are undirected, so an edge only needs to be listed by one of its nodes.
+    pub fn new(adjacency: Vec<Vec<usize>>) -> Graph {
+        if adjacency.len() > u16::MAX as usize {
+            panic!("Unsupported puzzle dimensions.");
+        }
+
+        let mut undirected: Vec<Vec<usize>> = vec![vec![]; adjacency.len()];
+        for (node, neighbours) in adjacency.iter().enumerate() {
+            for neighbour in neighbours {
+                if *neighbour >= adjacency.len() {
+                    panic!("Cannot connect a node outside the graph.");
+                }
+                // A node is never its own neighbour.
+                if *neighbour != node {
+                    undirected[node].push(*neighbour);
+                    undirected[*neighbour].push(node);
+                }
+            }
+        }
+        for neighbours in undirected.iter_mut() {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+
+        Graph {
+            adjacency: undirected,
+        }
+    }
+
+    pub fn get_node_count(&self) -> u16 {
+        self.adjacency.len() as u16
+    }
+    pub fn get_adjacency(&self) -> &Vec<Vec<usize>> {
+        &self.adjacency
+    }
+}
+
+impl Grid for Graph {
+    fn get_neighbours(
+        &self,
+        position: &Point,
+        _width: u16,
+        _height: u16,
+        _topology: Topology,
+    ) -> HashSet<Point> {
+        self.adjacency[position.x as usize]
+            .iter()
+            .map(|node| Point {
+                x: *node as u16,
+                y: 0,
+            })
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_grid(&self, other: &dyn Grid) -> bool {
+        eq_grid(self, other)
+    }
+
+    fn for_each_neighbour(
+        &self,
+        position: &Point,
+        _width: u16,
+        _height: u16,
+        _topology: Topology,
+        visit: &mut dyn FnMut(Point),
+    ) {
+        for node in &self.adjacency[position.x as usize] {
+            visit(Point {
+                x: *node as u16,
+                y: 0,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_adjacency_is_symmetric() {
+        for topology in [Topology::Bounded, Topology::Toroidal].iter() {
+            for i in 0..24 {
+                let position = point::from_index(i, 6);
+                let neighbours = Hex.get_neighbours(&position, 6, 4, *topology);
+
+                // Wrapping gives every tile a full set of neighbours.
+                if *topology == Topology::Toroidal {
+                    assert_eq!(6, neighbours.len());
+                }
+
+                // Each neighbour should consider this tile a neighbour too.
+                for neighbour in &neighbours {
+                    assert!(Hex
+                        .get_neighbours(neighbour, 6, 4, *topology)
+                        .contains(&position));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hex_toroidal_height() {
+        assert!(Hex.supports(5, 4, Topology::Toroidal));
+        assert!(Hex.supports(5, 5, Topology::Bounded));
+        assert!(!Hex.supports(5, 5, Topology::Toroidal));
+    }
+
+    #[test]
+    fn test_grid_equality() {
+        let grids: Vec<Box<dyn Grid>> = vec![
+            Box::new(Square),
+            Box::new(Hex),
+            Box::new(Neighbourhood::radius(1)),
+            Box::new(Neighbourhood::knight()),
+            Box::new(Graph::new(vec![vec![1], vec![]])),
+            Box::new(Graph::new(vec![vec![], vec![]])),
+        ];
+        // Grids only equal themselves, even when they have the same neighbours.
+        for (i, a) in grids.iter().enumerate() {
+            for (j, b) in grids.iter().enumerate() {
+                assert_eq!(i == j, **a == **b);
+            }
+        }
+        assert!(
+            *(&Neighbourhood::knight() as &dyn Grid) == *(&Neighbourhood::knight() as &dyn Grid)
+        );
+    }
+
+    #[test]
+    fn test_graph_is_undirected() {
+        // A square with one diagonal, listing each edge once plus a self loop.
+        let graph = Graph::new(vec![vec![1, 2], vec![3], vec![3, 2], vec![0]]);
+
+        assert_eq!(4, graph.get_node_count());
+        assert_eq!(
+            &vec![vec![1, 2, 3], vec![0, 3], vec![0, 3], vec![0, 1, 2]],
+            graph.get_adjacency()
+        );
+    }
+
+    #[test]
+    fn test_neighbourhood_offsets() {
+        // Custom offsets are mirrored and never include the tile itself.
+        let custom = Neighbourhood::new(vec![(0, 0), (2, 0), (-2, 0)]);
+        assert_eq!(&vec![(-2, 0), (2, 0)], custom.get_offsets());
+
+        // Built in rules have the expected number of neighbours.
+        assert_eq!(8, Neighbourhood::knight().get_offsets().len());
+        assert_eq!(24, Neighbourhood::radius(2).get_offsets().len());
+        assert_eq!(4, Neighbourhood::diamond(1).get_offsets().len());
+
+        // A radius of 1 matches the square grid.
+        let radius = Neighbourhood::radius(1);
+        for topology in [Topology::Bounded, Topology::Toroidal].iter() {
+            for i in 0..20 {
+                let position = point::from_index(i, 5);
+                assert_eq!(
+                    Square.get_neighbours(&position, 5, 4, *topology),
+                    radius.get_neighbours(&position, 5, 4, *topology)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_each_neighbour_matches_get_neighbours() {
+        let grids: Vec<Box<dyn Grid>> = vec![
+            Box::new(Square),
+            Box::new(Hex),
+            Box::new(Neighbourhood::knight()),
+            Box::new(Cube {
+                height: 2,
+                depth: 2,
+            }),
+            Box::new(Graph::new(vec![vec![1, 2], vec![2], vec![0], vec![]])),
+        ];
+        for grid in grids.iter() {
+            for topology in [Topology::Bounded, Topology::Toroidal].iter() {
+                // A narrow width makes toroidal offsets wrap onto the same tiles.
+                for i in 0..8 {
+                    let position = point::from_index(i, 2);
+                    let mut visited: HashSet<Point> = HashSet::new();
+                    grid.for_each_neighbour(&position, 2, 4, *topology, &mut |neighbour| {
+                        visited.insert(neighbour);
+                    });
+                    assert_eq!(grid.get_neighbours(&position, 2, 4, *topology), visited);
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,9 @@
+pub mod batch;
+pub mod grid;
 pub mod map;
 pub mod point;
+pub mod puzzle;
+pub mod render;
 pub mod solver;
 
 use std::collections::HashSet;
@@ -0,0 +1,278 @@
+//! A compact view of a map's state, holding each tile state as one bit per tile.
+
+use super::Map;
+use std::sync::Arc;
+
+/// A fixed size set of tile indices, stored as one bit per tile.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    /// Create an empty set with room for indices below `len`.
+    pub fn new(len: usize) -> Bitset {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] >> (index % 64) & 1 == 1
+    }
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    /// The number of indices in the set.
+    pub fn count(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Remove the indices that are in the `other` set.
+    pub fn difference_with(&mut self, other: &Bitset) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= !b;
+        }
+    }
+
+    /// Iterate over the indices in the set in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, word)| {
+            let mut remaining: u64 = *word;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                let bit: usize = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+                Some(w * 64 + bit)
+            })
+        })
+    }
+}
+
+/// The state of every tile of a map held as bitsets, along with each tile's value.
+/// The neighbours of each tile are shared with the map they came from.
+#[derive(Clone, Debug)]
+pub struct Bitboard {
+    width: u16,
+    height: u16,
+    exists: Bitset,
+    /// The tiles with at least one mine.
+    mines: Bitset,
+    flipped: Bitset,
+    /// The tiles with at least one flag.
+    flagged: Bitset,
+    /// The existing tiles that are neither flipped nor flagged.
+    hidden: Bitset,
+    values: Vec<i16>,
+    neighbours: Arc<Vec<Vec<usize>>>,
+}
+
+impl Bitboard {
+    /// Create a bitboard matching the current state of the `map`.
+    pub fn from_map(map: &Map) -> Bitboard {
+        let size: usize = map.get_tiles().len();
+        let mut bitboard = Bitboard {
+            width: map.get_width(),
+            height: map.get_height(),
+            exists: Bitset::new(size),
+            mines: Bitset::new(size),
+            flipped: Bitset::new(size),
+            flagged: Bitset::new(size),
+            hidden: Bitset::new(size),
+            values: vec![0; size],
+            neighbours: map.adjacency.clone(),
+        };
+        bitboard.sync(map);
+        bitboard
+    }
+
+    /// Update the tile states to match the `map`, which must have the same layout.
+    pub fn sync(&mut self, map: &Map) {
+        for (index, tile) in map.get_tiles().iter().enumerate() {
+            let sets = [
+                (&mut self.exists, map.tile_exists(index)),
+                (&mut self.mines, tile.is_mine()),
+                (&mut self.flipped, tile.flipped),
+                (&mut self.flagged, tile.is_flagged()),
+            ];
+            for (set, member) in sets {
+                if member {
+                    set.insert(index);
+                } else {
+                    set.remove(index);
+                }
+            }
+            self.values[index] = tile.value;
+        }
+
+        self.hidden = self.exists.clone();
+        self.hidden.difference_with(&self.flipped);
+        self.hidden.difference_with(&self.flagged);
+    }
+
+    pub fn get_width(&self) -> u16 {
+        self.width
+    }
+    pub fn get_height(&self) -> u16 {
+        self.height
+    }
+    pub fn get_exists(&self) -> &Bitset {
+        &self.exists
+    }
+    pub fn get_mines(&self) -> &Bitset {
+        &self.mines
+    }
+    pub fn get_flipped(&self) -> &Bitset {
+        &self.flipped
+    }
+    pub fn get_flagged(&self) -> &Bitset {
+        &self.flagged
+    }
+    pub fn get_value(&self, index: usize) -> i16 {
+        self.values[index]
+    }
+    pub fn get_neighbours(&self, index: usize) -> &[usize] {
+        &self.neighbours[index]
+    }
+
+    /// Get the existing tiles that are neither flipped nor flagged.
+    pub fn get_hidden(&self) -> &Bitset {
+        &self.hidden
+    }
+
+    /// Count the hidden tiles adjacent to the tile at `index`.
+    pub fn count_hidden_neighbours(&self, index: usize) -> u32 {
+        self.neighbours[index]
+            .iter()
+            .filter(|neighbour| self.hidden.contains(**neighbour))
+            .count() as u32
+    }
+
+    /// Find the groups of hidden tiles that share flipped tiles constraining them.
+    /// Tiles in separate groups can be solved independently of each other.
+    pub fn get_groups(&self) -> Vec<Bitset> {
+        let size: usize = self.values.len();
+        let mut grouped = Bitset::new(size);
+        let mut visited = Bitset::new(size);
+
+        let mut groups: Vec<Bitset> = Vec::new();
+        for start in self.flipped.iter() {
+            if visited.contains(start) || self.count_hidden_neighbours(start) == 0 {
+                continue;
+            }
+            visited.insert(start);
+
+            // Alternate between the hidden tiles around the clues found
+            // and the clues around the hidden tiles found.
+            let mut group = Bitset::new(size);
+            let mut clues: Vec<usize> = vec![start];
+            while let Some(clue) = clues.pop() {
+                for member in &self.neighbours[clue] {
+                    if !self.hidden.contains(*member) || grouped.contains(*member) {
+                        continue;
+                    }
+                    grouped.insert(*member);
+                    group.insert(*member);
+
+                    for neighbour in &self.neighbours[*member] {
+                        if self.flipped.contains(*neighbour) && !visited.contains(*neighbour) {
+                            visited.insert(*neighbour);
+                            clues.push(*neighbour);
+                        }
+                    }
+                }
+            }
+
+            groups.push(group);
+        }
+
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::point::Point;
+    use crate::solver::{self, GuessStrategy};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_bitset() {
+        let mut set = Bitset::new(130);
+        assert!(set.is_empty());
+        for index in [0, 63, 64, 129].iter() {
+            set.insert(*index);
+        }
+        set.remove(63);
+        assert_eq!(3, set.count());
+        assert!(set.contains(64) && !set.contains(63));
+        assert_eq!(vec![0, 64, 129], set.iter().collect::<Vec<usize>>());
+
+        let mut other = Bitset::new(130);
+        other.insert(129);
+        other.insert(5);
+        set.difference_with(&other);
+        assert_eq!(vec![0, 64], set.iter().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_large_board() {
+        // The neighbours are shared with the map rather than stored as a board-sized set per tile,
+        // and the opening is large enough to overflow a recursive flip.
+        let click = Point { x: 200, y: 200 };
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut map = map::generate_map_with_total_and_rng(400, 400, 8000, click.clone(), &mut rng);
+        map.flip(&click);
+        let bitboard = Bitboard::from_map(&map);
+        assert!(Arc::ptr_eq(&map.adjacency, &bitboard.neighbours));
+
+        let moves = solver::solve_with_rng(&map, GuessStrategy::LowestRisk, &mut rng);
+        assert!(moves.len() > 1000);
+    }
+
+    #[test]
+    fn test_groups() {
+        // Create a long strip with a mine at each end and clues revealed beside them.
+        let mines: HashSet<Point> = [Point { x: 0, y: 0 }, Point { x: 9, y: 0 }]
+            .iter()
+            .cloned()
+            .collect();
+        let mut map = map::generate_map_with_mines(10, 1, mines);
+        for x in [1, 2, 7, 8].iter() {
+            map.reveal(&Point { x: *x, y: 0 });
+        }
+        let mut bitboard = Bitboard::from_map(&map);
+
+        // Each clue constrains a single hidden tile, so they form separate groups
+        // and the middle is unconstrained.
+        let groups: Vec<Vec<usize>> = bitboard
+            .get_groups()
+            .iter()
+            .map(|group| group.iter().collect())
+            .collect();
+        assert_eq!(vec![vec![0], vec![3], vec![6], vec![9]], groups);
+        assert_eq!(1, bitboard.count_hidden_neighbours(1));
+
+        // Flags are not part of any group.
+        map.flag(&Point { x: 0, y: 0 });
+        bitboard.sync(&map);
+        assert_eq!(
+            vec![3],
+            bitboard.get_groups()[0].iter().collect::<Vec<usize>>()
+        );
+        assert_eq!(0, bitboard.count_hidden_neighbours(1));
+    }
+}
@@ -1,16 +1,23 @@
 //! This module contains tools for manipulating a puzzle map.
 
-use crate::point::{self, Point};
-use crate::solver::{Move, MoveType};
+use crate::grid::{Cube, Graph, Grid, Neighbourhood, Square};
+use crate::point::{self, Point, Point3, Topology};
+use crate::solver::{self, Move, MoveType};
 use rand;
-use rand::seq::IteratorRandom;
-use rand::thread_rng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::io::{self, Write};
 use std::iter::FromIterator;
+use std::sync::Arc;
 use std::vec::Vec;
 
+mod bitboard;
+
+pub use self::bitboard::{Bitboard, Bitset};
+
 /// Represents the completion state of a puzzle.
 #[derive(PartialEq, Clone, Debug)]
 pub enum Status {
@@ -19,21 +26,161 @@ pub enum Status {
     Complete,
 }
 
+/// How hidden mines move when a tile that could be either safe or a mine is flipped.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mode {
+    /// Mines never move.
+    Normal,
+    /// Mines move away from the flipped tile whenever a layout consistent with the revealed tiles allows it,
+    /// unless some hidden tile was certain to be safe and the player guessed anyway.
+    Kind,
+    /// Mines move onto the flipped tile whenever a layout consistent with the revealed tiles allows it.
+    Cruel,
+}
+
 /// Represents the state of a tile.
 #[derive(PartialEq, Clone)]
 pub struct Tile {
-    /// The number of adjacent tiles with mines on them.
-    pub value: u8,
-    /// If this tile is a mine.
-    pub mine: bool,
-    /// If this tile has been flagged.
-    pub flagged: bool,
+    /// The number of mines on adjacent tiles, with negative mines subtracting from it.
+    pub value: i16,
+    /// The number of mines on this tile, negative for negative mines.
+    pub mines: i8,
+    /// The number of flags placed on this tile, negative for negative flags.
+    pub flags: i8,
     /// If this tile has been flipped.
     pub flipped: bool,
 }
 
+impl Tile {
+    /// If this tile has at least one mine.
+    pub fn is_mine(&self) -> bool {
+        self.mines != 0
+    }
+    /// If this tile has at least one flag.
+    pub fn is_flagged(&self) -> bool {
+        self.flags != 0
+    }
+}
+
+/// Describes the shape of a map, independent of where its mines are.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    /// The width of the map.
+    pub width: u16,
+    /// The height of the map.
+    pub height: u16,
+    /// Which tiles exist, indexed by `Point::to_index`.
+    pub mask: Vec<bool>,
+    /// How the edges of the map connect.
+    pub topology: Topology,
+    /// How the tiles of the map are arranged.
+    pub grid: Arc<dyn Grid>,
+}
+
+impl PartialEq for Layout {
+    fn eq(&self, other: &Layout) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.mask == other.mask
+            && self.topology == other.topology
+            && *self.grid == *other.grid
+    }
+}
+
+impl Layout {
+    /// Create a rectangular layout with bounded edges.
+    pub fn new(width: u16, height: u16) -> Layout {
+        Layout {
+            width,
+            height,
+            mask: vec![true; width as usize * height as usize],
+            topology: Topology::Bounded,
+            grid: Arc::new(Square),
+        }
+    }
+
+    /// Create a rectangular layout with bounded edges where tiles touch the given `neighbourhood`.
+    ///
+    /// ```
+    /// use casspir::{grid, map, point};
+    /// use std::collections::HashSet;
+    ///
+    /// let layout = map::Layout::with_neighbourhood(5, 5, grid::Neighbourhood::knight());
+    /// let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+    /// let map = map::generate_map_with_layout_and_mines(layout, mines);
+    /// assert_eq!(1, map.get_tile(11).value);
+    /// assert_eq!(0, map.get_tile(1).value);
+    /// ```
+    pub fn with_neighbourhood(width: u16, height: u16, neighbourhood: Neighbourhood) -> Layout {
+        Layout {
+            grid: Arc::new(neighbourhood),
+            ..Layout::new(width, height)
+        }
+    }
+
+    /// Create a three dimensional layout with bounded edges.
+    /// Layers are stacked vertically, so the map is `height * depth` tall, see `Point3::to_point`.
+    pub fn new_3d(width: u16, height: u16, depth: u16) -> Layout {
+        let stacked_height: u32 = height as u32 * depth as u32;
+        if stacked_height > u16::MAX as u32 {
+            panic!("Unsupported puzzle dimensions.");
+        }
+        Layout {
+            width,
+            height: stacked_height as u16,
+            mask: vec![true; width as usize * stacked_height as usize],
+            topology: Topology::Bounded,
+            grid: Arc::new(Cube { height, depth }),
+        }
+    }
+
+    /// Create a layout from the nodes of a `graph`.
+    /// Nodes are laid out along a single row, so node `n` is at `Point { x: n, y: 0 }`.
+    pub fn from_graph(graph: Graph) -> Layout {
+        Layout {
+            width: graph.get_node_count(),
+            height: 1,
+            mask: vec![true; graph.get_node_count() as usize],
+            topology: Topology::Bounded,
+            grid: Arc::new(graph),
+        }
+    }
+
+    /// Build the list of existing adjacent tile indices for every tile index.
+    /// Each list is sorted and masked tiles have no neighbours.
+    pub fn build_adjacency(&self) -> Vec<Vec<usize>> {
+        if !self.grid.supports(self.width, self.height, self.topology) {
+            panic!("Unsupported puzzle dimensions for this grid.");
+        }
+        (0..self.mask.len())
+            .map(|i| {
+                let mut neighbours: Vec<usize> = Vec::new();
+                if !self.mask[i] {
+                    return neighbours;
+                }
+                let position = point::from_index(i, self.width);
+                self.grid.for_each_neighbour(
+                    &position,
+                    self.width,
+                    self.height,
+                    self.topology,
+                    &mut |neighbour| {
+                        let index: usize = neighbour.to_index(self.width);
+                        if self.mask[index] {
+                            neighbours.push(index);
+                        }
+                    },
+                );
+                neighbours.sort_unstable();
+                neighbours.dedup();
+                neighbours
+            })
+            .collect()
+    }
+}
+
 /// Represents the state of a map (a game board).
-#[derive(PartialEq, Clone)]
+#[derive(Clone)]
 pub struct Map {
     /// The width of the map.
     width: u16,
@@ -45,10 +192,41 @@ pub struct Map {
     mines_remaining: u32,
     /// The number of tiles flipped.
     tiles_flipped: u32,
+    /// The number of tiles with at least one mine.
+    tiles_mined: u32,
+    /// The most mines a single tile can hold.
+    tile_capacity: u8,
+    /// If tiles can hold negative mines.
+    negative_mines: bool,
+    /// How hidden mines move when flipping uncertain tiles.
+    mode: Mode,
     /// The completion state of this map.
     status: Status,
     /// The tiles of the map.
     tiles: Vec<Tile>,
+    /// Which tiles exist on the map.
+    /// Masked out tiles are never mines and take no part in play.
+    mask: Vec<bool>,
+    /// How the edges of the map connect.
+    topology: Topology,
+    /// How the tiles of the map are arranged.
+    grid: Arc<dyn Grid>,
+    /// The indices of the existing tiles adjacent to each tile.
+    adjacency: Arc<Vec<Vec<usize>>>,
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.total_mines == other.total_mines
+            && self.mines_remaining == other.mines_remaining
+            && self.tiles_flipped == other.tiles_flipped
+            && self.tile_capacity == other.tile_capacity
+            && self.negative_mines == other.negative_mines
+            && self.mode == other.mode
+            && self.status == other.status
+            && self.tiles == other.tiles
+            && self.get_layout() == other.get_layout()
+    }
 }
 
 impl Map {
@@ -64,6 +242,9 @@ impl Map {
     pub fn get_size(&self) -> u32 {
         self.width as u32 * self.height as u32
     }
+    pub fn get_tile_count(&self) -> u32 {
+        self.mask.iter().filter(|exists| **exists).count() as u32
+    }
     pub fn get_tiles_flipped(&self) -> u32 {
         self.tiles_flipped
     }
@@ -73,29 +254,93 @@ impl Map {
     pub fn get_tile(&self, index: usize) -> &Tile {
         &self.tiles[index]
     }
+    pub fn get_total_mines(&self) -> u32 {
+        self.total_mines
+    }
     pub fn get_mines_remaining(&self) -> u32 {
         self.mines_remaining
     }
+    pub fn get_tile_capacity(&self) -> u8 {
+        self.tile_capacity
+    }
+    pub fn has_negative_mines(&self) -> bool {
+        self.negative_mines
+    }
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+    pub fn get_mask(&self) -> &Vec<bool> {
+        &self.mask
+    }
+    pub fn tile_exists(&self, index: usize) -> bool {
+        self.mask[index]
+    }
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+    pub fn get_grid(&self) -> &dyn Grid {
+        self.grid.as_ref()
+    }
+    pub fn get_layout(&self) -> Layout {
+        Layout {
+            width: self.width,
+            height: self.height,
+            mask: self.mask.clone(),
+            topology: self.topology,
+            grid: self.grid.clone(),
+        }
+    }
+
+    /// Get the indices of the existing tiles adjacent to the tile at `index`.
+    pub fn get_adjacent(&self, index: usize) -> &[usize] {
+        &self.adjacency[index]
+    }
+
+    /// Get the existing tiles adjacent to the given `position`.
+    pub fn get_neighbours(&self, position: &Point) -> HashSet<Point> {
+        self.neighbours(position).collect()
+    }
+
+    /// Iterate over the existing tiles adjacent to the given `position` in index order,
+    /// without allocating.
+    pub fn neighbours(&self, position: &Point) -> impl Iterator<Item = Point> + '_ {
+        self.get_adjacent(position.to_index(self.width))
+            .iter()
+            .map(move |index| point::from_index(*index, self.width))
+    }
 
     // Write an ascii representation of the current map state to `writer`.
+    // Each tile is a single character, see `value_symbol` for how values are written.
     pub fn print(&self, writer: &mut dyn Write, revealed: bool) -> io::Result<()> {
         for i in 0..self.get_tiles().len() {
             if (i % self.width as usize) == 0 {
-                write!(writer, "\n")?;
+                self.grid
+                    .write_row_start(writer, (i / self.width as usize) as u16)?;
+            } else {
+                self.grid.write_tile_separator(writer)?;
             }
-            if self.get_tile(i).flipped || revealed {
-                if self.get_tile(i).mine {
+            if !self.mask[i] {
+                write!(writer, " ")?;
+            } else if self.get_tile(i).flipped || revealed {
+                if self.get_tile(i).mines > 0 {
                     write!(writer, "*")?;
+                } else if self.get_tile(i).mines < 0 {
+                    write!(writer, "~")?;
                 } else {
-                    write!(writer, "{}", self.get_tile(i).value)?;
+                    write!(writer, "{}", value_symbol(self.get_tile(i).value))?;
                 }
-            } else if self.get_tile(i).flagged {
+            } else if self.get_tile(i).flags > 0 {
                 write!(writer, "^")?;
+            } else if self.get_tile(i).flags < 0 {
+                write!(writer, "v")?;
             } else {
                 write!(writer, "#")?;
             }
         }
-        write!(writer, "\n")?;
+        writeln!(writer)?;
 
         Ok(())
     }
@@ -103,15 +348,18 @@ impl Map {
     // Apply a queue of moves.
     pub fn apply_moves(&mut self, moves: &VecDeque<Move>) {
         for play in moves {
-            if play.move_type == MoveType::Flip {
-                self.flip(&play.position);
-            } else {
-                self.flag(&play.position);
+            match play.move_type {
+                MoveType::Flip => {
+                    self.flip(&play.position);
+                }
+                MoveType::Flag => self.flag(&play.position),
+                MoveType::NegativeFlag => self.flag_negative(&play.position),
             }
         }
     }
 
     /// Flags or unflags a tile at the given `position`.
+    /// Tiles that can hold several mines cycle through each flag count before unflagging.
     pub fn flag(&mut self, position: &Point) {
         if self.status != Status::InProgress {
             return;
@@ -119,35 +367,73 @@ impl Map {
 
         let index: usize = position.to_index(self.width);
 
-        if self.tiles[index].flipped {
+        if self.tiles[index].flipped || !self.mask[index] {
+            return;
+        }
+
+        let flags: i8 = self.tiles[index].flags;
+        if flags >= 0 && flags < self.tile_capacity as i8 && self.mines_remaining > 0 {
+            self.tiles[index].flags += 1;
+            self.mines_remaining -= 1;
+        } else {
+            self.mines_remaining += flags.unsigned_abs() as u32;
+            self.tiles[index].flags = 0;
+        }
+    }
+
+    /// Places or removes negative flags on a tile at the given `position`.
+    /// Tiles that can hold several mines cycle through each flag count before unflagging.
+    /// Does nothing unless the map has negative mines.
+    pub fn flag_negative(&mut self, position: &Point) {
+        if self.status != Status::InProgress || !self.negative_mines {
             return;
         }
 
-        if self.tiles[index].flagged {
-            self.tiles[index].flagged = false;
-            self.mines_remaining += 1;
-        } else if self.mines_remaining > 0 {
-            self.tiles[index].flagged = true;
+        let index: usize = position.to_index(self.width);
+
+        if self.tiles[index].flipped || !self.mask[index] {
+            return;
+        }
+
+        let flags: i8 = self.tiles[index].flags;
+        if flags <= 0 && flags > -(self.tile_capacity as i8) && self.mines_remaining > 0 {
+            self.tiles[index].flags -= 1;
             self.mines_remaining -= 1;
+        } else {
+            self.mines_remaining += flags.unsigned_abs() as u32;
+            self.tiles[index].flags = 0;
         }
     }
 
+    /// Produces a copy of the map with every flag removed, since flags are only the player's guesses.
+    pub fn without_flags(&self) -> Map {
+        let mut map: Map = self.clone();
+        for tile in map.tiles.iter_mut() {
+            tile.flags = 0;
+        }
+        map.mines_remaining = map.total_mines;
+        map
+    }
+
     /// Flip the tile at the given `position`.
     /// This can trigger a recursive flip that flips all connected 0 value tiles.
     pub fn flip(&mut self, position: &Point) -> u32 {
         let index: usize = position.to_index(self.width);
         let mut flipped: u32 = 0;
 
+        if !self.mask[index] {
+            return 0;
+        }
+
         if self.tiles[index].flipped {
             if self.is_tile_satisfied(position) {
-                let neighbours: HashSet<Point> =
-                    point::get_neighbours(position, self.width, self.height);
-                for neighbour in &neighbours {
-                    flipped += self.flip_recurse(neighbour);
+                let adjacency = self.adjacency.clone();
+                for neighbour in &adjacency[index] {
+                    flipped += self.flip_hidden(*neighbour);
                 }
             }
-        } else if !self.tiles[position.to_index(self.width)].flagged {
-            flipped = self.flip_recurse(position);
+        } else {
+            flipped = self.flip_hidden(index);
         }
 
         self.check_completed();
@@ -155,49 +441,147 @@ impl Map {
         flipped
     }
 
-    /// Recursively flip tile neighbours that have a value of 0.
-    fn flip_recurse(&mut self, position: &Point) -> u32 {
-        if self.status != Status::InProgress {
+    /// Flip the hidden, unflagged tile at `index`, moving the mines first according to the map's mode.
+    fn flip_hidden(&mut self, index: usize) -> u32 {
+        let tile: &Tile = &self.tiles[index];
+        if tile.flipped || tile.is_flagged() {
             return 0;
         }
+        if self.mode != Mode::Normal && self.status == Status::InProgress {
+            self.adapt(index);
+        }
+        self.flip_recurse(index)
+    }
+
+    /// Move the hidden mines before the tile at `index` is flipped, according to the map's mode.
+    /// Mines only move if some layout consistent with the revealed tiles gives the tile the
+    /// outcome the mode wants, so a tile that is certain to be safe or a mine is left alone.
+    /// A kind map only spares guesses that were forced, with no hidden tile certain to be safe.
+    fn adapt(&mut self, index: usize) {
+        let mined: bool = self.mode == Mode::Cruel;
+        if self.tiles[index].is_mine() == mined {
+            return;
+        }
 
+        // Flags are only the player's guesses, so leave them out of what's known.
+        let known: Map = self.without_flags();
+
+        if self.mode == Mode::Kind {
+            let forced: bool = solver::mine_probabilities(&known).is_none_or(|probabilities| {
+                (0..self.tiles.len()).all(|i| {
+                    !self.tile_exists(i)
+                        || self.tiles[i].flipped
+                        || probabilities[i] >= solver::CERTAINTY_TOLERANCE
+                })
+            });
+            if !forced {
+                return;
+            }
+        }
+
+        if let Some(mines) =
+            solver::sample_restricted_mine_counts(&known, &mut thread_rng(), index, mined)
+        {
+            self.place_mines(&mines);
+        }
+    }
+
+    /// Replace the mines on the map with the given number of `mines` on each mined tile.
+    fn place_mines(&mut self, mines: &HashMap<Point, i8>) {
+        for tile in self.tiles.iter_mut() {
+            tile.mines = 0;
+            tile.value = 0;
+        }
+
+        self.tiles_mined = 0;
+        for (mine, count) in mines {
+            if *count == 0 {
+                continue;
+            }
+            let index: usize = mine.to_index(self.width);
+            self.tiles[index].mines = *count;
+            self.tiles_mined += 1;
+            for neighbour in &self.adjacency[index] {
+                self.tiles[*neighbour].value += *count as i16;
+            }
+        }
+    }
+
+    /// Flip only the tile at the given `position`, without flipping any of its neighbours.
+    /// Used to reveal clues, returns 1 if the tile was flipped.
+    pub fn reveal(&mut self, position: &Point) -> u32 {
         let index: usize = position.to_index(self.width);
 
-        if self.tiles[index].flipped || self.tiles[index].flagged {
+        if self.status != Status::InProgress || !self.mask[index] || self.tiles[index].flipped {
             return 0;
         }
 
         self.tiles[index].flipped = true;
         self.tiles_flipped += 1;
 
-        if self.tiles[index].mine {
+        if self.tiles[index].is_mine() {
             self.status = Status::Failed;
-            return 1;
         }
+        self.check_completed();
 
-        if self.tiles[index].value != 0 {
-            return 1;
-        }
+        1
+    }
 
-        let neighbours: HashSet<Point> = point::get_neighbours(position, self.width, self.height);
+    /// Flip the tile at `index` and the neighbours of any flipped tiles that have a value of 0.
+    /// With negative mines a value of 0 can hide mines that cancel out,
+    /// so only tiles with no mines around them at all are expanded.
+    fn flip_recurse(&mut self, index: usize) -> u32 {
+        // Walk the cascade with an explicit stack so large openings can't overflow the call stack.
+        // Neighbours are pushed in reverse to flip tiles in the same order as a recursive walk.
+        let adjacency = self.adjacency.clone();
+        let mut stack: Vec<usize> = vec![index];
         let mut flipped: u32 = 0;
-        for neighbour in &neighbours {
-            flipped += self.flip_recurse(neighbour);
+        while let Some(index) = stack.pop() {
+            if self.status != Status::InProgress {
+                break;
+            }
+
+            if self.tiles[index].flipped || self.tiles[index].is_flagged() {
+                continue;
+            }
+
+            self.tiles[index].flipped = true;
+            self.tiles_flipped += 1;
+
+            if self.tiles[index].is_mine() {
+                self.status = Status::Failed;
+                flipped += 1;
+                break;
+            }
+
+            if self.tiles[index].value != 0
+                || (self.negative_mines && self.has_adjacent_mine(index))
+            {
+                flipped += 1;
+                continue;
+            }
+
+            stack.extend(adjacency[index].iter().rev());
         }
 
         flipped
     }
 
+    /// Checks if any tile adjacent to the tile at `index` holds a mine.
+    fn has_adjacent_mine(&self, index: usize) -> bool {
+        self.adjacency[index]
+            .iter()
+            .any(|neighbour| self.tiles[*neighbour].is_mine())
+    }
+
     /// Checks if the tile at the given `position` is connected the same number of flags as it's value.
     pub fn is_tile_satisfied(&self, position: &Point) -> bool {
-        let tile: &Tile = &self.tiles[position.to_index(self.width)];
-        let neighbours: HashSet<Point> = point::get_neighbours(position, self.width, self.height);
+        let index: usize = position.to_index(self.width);
+        let tile: &Tile = &self.tiles[index];
 
-        let mut flags: u8 = 0;
-        for neighbour in neighbours {
-            if self.tiles[neighbour.to_index(self.width)].flagged {
-                flags += 1;
-            }
+        let mut flags: i16 = 0;
+        for neighbour in self.get_adjacent(index) {
+            flags += self.tiles[*neighbour].flags as i16;
         }
 
         flags == tile.value
@@ -209,24 +593,47 @@ impl Map {
             return;
         }
 
-        if (self.tiles_flipped + self.total_mines) as usize == self.tiles.len() {
+        if self.tiles_flipped + self.tiles_mined == self.get_tile_count() {
             self.status = Status::Complete;
         }
     }
 }
 
+/// Get the single character used to print a tile `value`. Values from 0 to 9 are digits,
+/// larger values count up from `A` for 10 and negative values count down from `a` for -1.
+/// Values beyond the alphabet are printed as `+` or `-`.
+///
+/// ```
+/// use casspir::map;
+/// assert_eq!('3', map::value_symbol(3));
+/// assert_eq!('A', map::value_symbol(10));
+/// assert_eq!('b', map::value_symbol(-2));
+/// ```
+pub fn value_symbol(value: i16) -> char {
+    match value {
+        0..=9 => (b'0' + value as u8) as char,
+        10..=35 => (b'A' + (value - 10) as u8) as char,
+        -26..=-1 => (b'a' + (-1 - value) as u8) as char,
+        _ if value > 0 => '+',
+        _ => '-',
+    }
+}
+
 /// Generate a map based on a given `difficulty` and initial `click`.
 pub fn generate_map_with_difficulty(width: u16, height: u16, difficulty: u8, click: Point) -> Map {
     // Initialise a vector of empty tiles.
     let mut tiles = vec![
         Tile {
             value: 0,
-            mine: false,
-            flagged: false,
+            mines: 0,
+            flags: 0,
             flipped: false,
         };
-        (width * height) as usize
+        width as usize * height as usize
     ];
+    let layout = Layout::new(width, height);
+    let adjacency = layout.build_adjacency();
+
     // Choose a mine probability based on the given difficulty.
     let mine_probability: f32 = ((difficulty as f32) + 20.0) / 512.0;
 
@@ -237,12 +644,12 @@ pub fn generate_map_with_difficulty(width: u16, height: u16, difficulty: u8, cli
 
         // Don't make the first clicked tile a mine.
         if position != click && rand::random::<f32>() < mine_probability {
-            tiles[i].mine = true;
+            tiles[i].mines = 1;
             total_mines += 1;
 
             // Increment the value of neighbouring tiles.
-            for point in point::get_neighbours(&position, width, height) {
-                tiles[point.to_index(width)].value += 1;
+            for neighbour in &adjacency[i] {
+                tiles[*neighbour].value += 1;
             }
         }
     }
@@ -254,55 +661,334 @@ pub fn generate_map_with_difficulty(width: u16, height: u16, difficulty: u8, cli
         total_mines,
         mines_remaining: total_mines,
         tiles_flipped: 0,
+        tiles_mined: total_mines,
+        tile_capacity: 1,
+        negative_mines: false,
+        mode: Mode::Normal,
         status: Status::InProgress,
         tiles,
+        mask: layout.mask,
+        topology: layout.topology,
+        grid: layout.grid,
+        adjacency: Arc::new(adjacency),
     };
-    map.flip_recurse(&click);
+    map.flip(&click);
     map
 }
 
-fn generate_mines_unchecked(width: u16, height: u16, total: u32) -> Vec<Point> {
+fn generate_mines_unchecked<R: Rng + ?Sized>(
+    width: u16,
+    height: u16,
+    total: u32,
+    rng: &mut R,
+) -> Vec<Point> {
     (0..width)
         .flat_map(|i| (0..height).map(move |j| Point { x: i, y: j }))
-        .choose_multiple(&mut thread_rng(), total as usize)
+        .choose_multiple(rng, total as usize)
 }
 
 /// Generate a map based on a given `total` number of mines and initial `click`.
 pub fn generate_map_with_total(width: u16, height: u16, total: u32, click: Point) -> Map {
-    let mut mines = generate_mines_unchecked(width, height, total);
+    generate_map_with_total_and_rng(width, height, total, click, &mut thread_rng())
+}
+
+/// Generate a map like `generate_map_with_total`, placing mines with the given `rng`.
+/// The same seeded `rng` always produces the same map.
+///
+/// ```
+/// use casspir::{map, point};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let click = point::Point { x: 0, y: 0 };
+/// let a = map::generate_map_with_total_and_rng(9, 9, 10, click.clone(), &mut StdRng::seed_from_u64(1));
+/// let b = map::generate_map_with_total_and_rng(9, 9, 10, click, &mut StdRng::seed_from_u64(1));
+/// assert!(a == b);
+/// ```
+pub fn generate_map_with_total_and_rng<R: Rng + ?Sized>(
+    width: u16,
+    height: u16,
+    total: u32,
+    click: Point,
+    rng: &mut R,
+) -> Map {
+    let mut mines = generate_mines_unchecked(width, height, total, rng);
     while mines.contains(&click) {
-        mines = generate_mines_unchecked(width, height, total)
+        mines = generate_mines_unchecked(width, height, total, rng)
+    }
+    generate_map_with_mines(width, height, HashSet::from_iter(mines))
+}
+
+/// How many maps `generate_no_guess_map` tries before giving up.
+const NO_GUESS_ATTEMPTS: u32 = 1000;
+
+/// Generate a map like `generate_map_with_total_and_rng` that can be solved from the
+/// initial `click` without guessing, with every move certain over all mine layouts
+/// consistent with what is revealed. The `click` is always an opening.
+/// Returns `None` if no such map was found, which is likely when mines are dense.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let click = point::Point { x: 4, y: 4 };
+/// let mut rng = StdRng::seed_from_u64(1);
+/// let mut map = map::generate_no_guess_map(9, 9, 10, click.clone(), &mut rng).unwrap();
+/// map.flip(&click);
+/// assert_eq!(0, solver::rate(&map).guesses);
+/// ```
+pub fn generate_no_guess_map<R: Rng + ?Sized>(
+    width: u16,
+    height: u16,
+    total: u32,
+    click: Point,
+    rng: &mut R,
+) -> Option<Map> {
+    let index: usize = click.to_index(width);
+    for _ in 0..NO_GUESS_ATTEMPTS {
+        let map = generate_map_with_total_and_rng(width, height, total, click.clone(), rng);
+        if map.get_tile(index).value != 0 {
+            continue;
+        }
+
+        let mut start = map.clone();
+        start.flip(&click);
+        let moves = solver::solve_with_rng(&start, solver::GuessStrategy::LowestRisk, rng);
+        let mut solved = start.clone();
+        solved.apply_moves(&moves);
+        if *solved.get_status() == Status::Complete && solver::count_guesses(&start, &moves) == 0 {
+            return Some(map);
+        }
     }
-    generate_map_with_mines(width, height, HashSet::from_iter(mines.into_iter()))
+    None
 }
 
 /// Generate a map with given mine locations.
 pub fn generate_map_with_mines(width: u16, height: u16, mines: HashSet<Point>) -> Map {
+    generate_map_with_layout_and_mines(Layout::new(width, height), mines)
+}
+
+/// Generate a map shaped by the given `mask` with a `total` number of mines.
+/// The `mask` is indexed by `Point::to_index` and marks which tiles exist,
+/// the initial `click` is never a mine. Panics if the mines don't fit on the other tiles.
+///
+/// ```
+/// use casspir::{map, point};
+/// // A 5x5 donut.
+/// let mask: Vec<bool> = (0..25).map(|i| i != 12).collect();
+/// let map = map::generate_map_with_mask(5, 5, mask, 4, point::Point { x: 0, y: 0 });
+/// assert_eq!(24, map.get_tile_count());
+/// assert_eq!(4, map.get_mines_remaining());
+/// assert!(!map.get_tile(12).is_mine());
+/// ```
+pub fn generate_map_with_mask(
+    width: u16,
+    height: u16,
+    mask: Vec<bool>,
+    total: u32,
+    click: Point,
+) -> Map {
+    let layout = Layout {
+        mask,
+        ..Layout::new(width, height)
+    };
+    generate_map_with_layout(layout, total, click)
+}
+
+/// Generate a map shaped by the given `mask` with given mine locations.
+pub fn generate_map_with_mines_and_mask(
+    width: u16,
+    height: u16,
+    mask: Vec<bool>,
+    mines: HashSet<Point>,
+) -> Map {
+    let layout = Layout {
+        mask,
+        ..Layout::new(width, height)
+    };
+    generate_map_with_layout_and_mines(layout, mines)
+}
+
+/// Generate a map with the given `layout` and a `total` number of mines.
+/// The initial `click` is never a mine, panics if the mines don't fit on the other tiles.
+///
+/// ```
+/// use casspir::{map, point};
+/// let layout = map::Layout {
+///     topology: point::Topology::Toroidal,
+///     ..map::Layout::new(8, 8)
+/// };
+/// let map = map::generate_map_with_layout(layout, 10, point::Point { x: 0, y: 0 });
+/// assert_eq!(point::Topology::Toroidal, map.get_topology());
+/// assert_eq!(10, map.get_mines_remaining());
+/// ```
+pub fn generate_map_with_layout(layout: Layout, total: u32, click: Point) -> Map {
+    let click_index: usize = click.to_index(layout.width);
+    check_mine_total(&layout, total, 1, click_index);
+    let mines: Vec<Point> = (0..layout.mask.len())
+        .filter(|i| layout.mask[*i] && *i != click_index)
+        .map(|i| point::from_index(i, layout.width))
+        .choose_multiple(&mut thread_rng(), total as usize);
+    generate_map_with_layout_and_mines(layout, HashSet::from_iter(mines))
+}
+
+/// Panics unless a `total` number of mines fit on the existing tiles of the `layout`
+/// other than the one at `click_index`, with up to `capacity` mines on each.
+fn check_mine_total(layout: &Layout, total: u32, capacity: u8, click_index: usize) {
+    let tiles: usize = (0..layout.mask.len())
+        .filter(|i| layout.mask[*i] && *i != click_index)
+        .count();
+    if total as usize > tiles * capacity as usize {
+        panic!("Cannot place more mines than the puzzle can hold.");
+    }
+}
+
+/// Panics unless each tile can hold at least one mine, and no more than a tile's mine count can record.
+fn check_capacity(capacity: u8) {
+    if !(1..=i8::MAX as u8).contains(&capacity) {
+        panic!("Tiles must hold between 1 and {} mines.", i8::MAX);
+    }
+}
+
+/// Generate a map with the given `layout` and mine locations.
+pub fn generate_map_with_layout_and_mines(layout: Layout, mines: HashSet<Point>) -> Map {
+    let mines: HashMap<Point, i8> = mines.into_iter().map(|mine| (mine, 1)).collect();
+    generate_map_with_layout_and_mine_counts(layout, mines, 1)
+}
+
+/// Generate a map with the given `layout` where each tile can hold up to `capacity` mines.
+/// A `total` number of mines are spread over the tiles, the initial `click` is never a mine.
+/// Panics unless `capacity` is between 1 and `i8::MAX`.
+///
+/// ```
+/// use casspir::{map, point};
+/// let layout = map::Layout::new(8, 8);
+/// let map = map::generate_map_with_capacity(layout, 20, 3, point::Point { x: 0, y: 0 });
+/// assert_eq!(3, map.get_tile_capacity());
+/// assert_eq!(20, map.get_mines_remaining());
+/// assert!(map.get_tiles().iter().all(|tile| tile.mines <= 3));
+/// ```
+pub fn generate_map_with_capacity(layout: Layout, total: u32, capacity: u8, click: Point) -> Map {
+    // Give every tile a slot per mine it can hold and fill a random selection of them.
+    let click_index: usize = click.to_index(layout.width);
+    check_capacity(capacity);
+    check_mine_total(&layout, total, capacity, click_index);
+    let slots: Vec<usize> = (0..layout.mask.len())
+        .filter(|i| layout.mask[*i] && *i != click_index)
+        .flat_map(|i| std::iter::repeat_n(i, capacity as usize))
+        .choose_multiple(&mut thread_rng(), total as usize);
+
+    let mut mines: HashMap<Point, i8> = HashMap::new();
+    for index in slots {
+        *mines
+            .entry(point::from_index(index, layout.width))
+            .or_insert(0) += 1;
+    }
+    generate_map_with_layout_and_mine_counts(layout, mines, capacity)
+}
+
+/// Generate a map with the given `layout` where some of the `total` number of mines are negative,
+/// subtracting one from the value of their neighbours instead of adding one.
+/// The `negative_ratio` is the proportion of negative mines, so 0.25 gives three positive
+/// mines for every negative one. The initial `click` is never a mine.
+///
+/// ```
+/// use casspir::{map, point};
+/// let layout = map::Layout::new(10, 10);
+/// let map = map::generate_map_with_negative_mines(layout, 20, 0.25, point::Point { x: 0, y: 0 });
+/// assert!(map.has_negative_mines());
+/// assert_eq!(20, map.get_mines_remaining());
+/// assert_eq!(5, map.get_tiles().iter().filter(|tile| tile.mines < 0).count());
+/// ```
+pub fn generate_map_with_negative_mines(
+    layout: Layout,
+    total: u32,
+    negative_ratio: f32,
+    click: Point,
+) -> Map {
+    if !(0.0..=1.0).contains(&negative_ratio) {
+        panic!("The negative ratio must be between 0 and 1.");
+    }
+    let negative: usize = (total as f32 * negative_ratio).round() as usize;
+    let click_index: usize = click.to_index(layout.width);
+    check_mine_total(&layout, total, 1, click_index);
+    let mut rng = thread_rng();
+    let mut mines: Vec<Point> = (0..layout.mask.len())
+        .filter(|i| layout.mask[*i] && *i != click_index)
+        .map(|i| point::from_index(i, layout.width))
+        .choose_multiple(&mut rng, total as usize);
+    mines.shuffle(&mut rng);
+
+    let mines: HashMap<Point, i8> = mines
+        .into_iter()
+        .enumerate()
+        .map(|(i, mine)| (mine, if i < negative { -1 } else { 1 }))
+        .collect();
+    let mut map = generate_map_with_layout_and_mine_counts(layout, mines, 1);
+    map.negative_mines = negative_ratio > 0.0;
+    map
+}
+
+/// Generate a map with the given `layout` and the number of mines on each mined tile.
+/// Each tile can hold up to `capacity` mines, negative counts place negative mines.
+/// Panics unless `capacity` is between 1 and `i8::MAX`.
+pub fn generate_map_with_layout_and_mine_counts(
+    layout: Layout,
+    mines: HashMap<Point, i8>,
+    capacity: u8,
+) -> Map {
+    check_capacity(capacity);
+    if layout.mask.len() != layout.width as usize * layout.height as usize {
+        panic!("The mask must cover the puzzle bounds.");
+    }
+
+    let adjacency = layout.build_adjacency();
+    let Layout {
+        width,
+        height,
+        mask,
+        topology,
+        grid,
+    } = layout;
+
     // Initialise a vector of empty tiles.
     let mut tiles = vec![
         Tile {
             value: 0,
-            mine: false,
-            flagged: false,
+            mines: 0,
+            flags: 0,
             flipped: false,
         };
         width as usize * height as usize
     ];
 
-    // Loop over the tiles and turn into a mine with the calculated probability.
-    let total_mines: u32 = mines.len() as u32;
-    for mine in &mines {
+    // Loop over the given mines and place them on the tiles.
+    let mut total_mines: u32 = 0;
+    let mut tiles_mined: u32 = 0;
+    for (mine, count) in &mines {
         // Ensure the mine is within the puzzle size.
         let index: usize = mine.to_index(width);
         if index > ((width as usize * height as usize) - 1) {
             panic!("Cannot place a mine outside the puzzle bounds.");
         }
+        if !mask[index] {
+            panic!("Cannot place a mine on a masked tile.");
+        }
+        if count.unsigned_abs() > capacity {
+            panic!("Cannot place more mines on a tile than it can hold.");
+        }
+        if *count == 0 {
+            continue;
+        }
         // Set as mine.
-        tiles[index].mine = true;
+        tiles[index].mines = *count;
+        total_mines += count.unsigned_abs() as u32;
+        tiles_mined += 1;
 
         // Increment the value of neighbouring tiles.
-        for point in point::get_neighbours(mine, width, height) {
-            tiles[point.to_index(width)].value += 1;
+        for neighbour in &adjacency[index] {
+            tiles[*neighbour].value += *count as i16;
         }
     }
 
@@ -313,14 +999,89 @@ pub fn generate_map_with_mines(width: u16, height: u16, mines: HashSet<Point>) -
         total_mines,
         mines_remaining: total_mines,
         tiles_flipped: 0,
+        tiles_mined,
+        tile_capacity: capacity,
+        negative_mines: mines.values().any(|count| *count < 0),
+        mode: Mode::Normal,
         status: Status::InProgress,
         tiles,
+        mask,
+        topology,
+        grid,
+        adjacency: Arc::new(adjacency),
     }
 }
 
+/// Generate a three dimensional map with a `total` number of mines.
+/// The initial `click` is never a mine.
+///
+/// ```
+/// use casspir::{map, point};
+/// let click = point::Point3 { x: 1, y: 2, z: 3 };
+/// let mut map = map::generate_3d_map_with_total(4, 4, 4, 6, click.clone());
+/// assert_eq!(64, map.get_tile_count());
+/// assert_eq!(6, map.get_mines_remaining());
+///
+/// map.flip(&click.to_point(4));
+/// assert_ne!(map::Status::Failed, *map.get_status());
+/// ```
+pub fn generate_3d_map_with_total(
+    width: u16,
+    height: u16,
+    depth: u16,
+    total: u32,
+    click: Point3,
+) -> Map {
+    generate_map_with_layout(
+        Layout::new_3d(width, height, depth),
+        total,
+        click.to_point(height),
+    )
+}
+
+/// Generate a three dimensional map with given mine locations.
+pub fn generate_3d_map_with_mines(
+    width: u16,
+    height: u16,
+    depth: u16,
+    mines: HashSet<Point3>,
+) -> Map {
+    let layout = Layout::new_3d(width, height, depth);
+    let mines: HashSet<Point> = mines.iter().map(|mine| mine.to_point(height)).collect();
+    generate_map_with_layout_and_mines(layout, mines)
+}
+
+/// Generate a map on a graph given as an `adjacency` list, with mines on the given nodes.
+/// The number of nodes is the length of the adjacency list and edges are undirected.
+/// Nodes are addressed as points along a single row, so node `n` is at `Point { x: n, y: 0 }`.
+///
+/// ```
+/// use casspir::{map, point};
+/// use std::collections::HashSet;
+/// // A triangle with a tail.
+/// let adjacency = vec![vec![1, 2], vec![2], vec![3], vec![]];
+/// let mines: HashSet<usize> = [0].iter().cloned().collect();
+/// let mut map = map::generate_map_with_graph(adjacency, mines);
+/// assert_eq!(4, map.get_tile_count());
+/// assert_eq!(0, map.get_tile(3).value);
+///
+/// map.flip(&point::Point { x: 3, y: 0 });
+/// map.flip(&point::Point { x: 1, y: 0 });
+/// assert_eq!(map::Status::Complete, *map.get_status());
+/// ```
+pub fn generate_map_with_graph(adjacency: Vec<Vec<usize>>, mines: HashSet<usize>) -> Map {
+    let layout = Layout::from_graph(Graph::new(adjacency));
+    let mines: HashSet<Point> = mines
+        .into_iter()
+        .map(|node| point::from_index(node, layout.width))
+        .collect();
+    generate_map_with_layout_and_mines(layout, mines)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grid::Hex;
     use crate::{map, solver};
 
     #[test]
@@ -621,4 +1382,497 @@ mod tests {
             string
         );
     }
+
+    #[test]
+    fn test_masked_map() {
+        // Create a 5x5 map with a wall across the middle.
+        let mask: Vec<bool> = (0..25).map(|i| !(10..15).contains(&i)).collect();
+        let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines_and_mask(5, 5, mask, mines);
+
+        // Masked tiles don't count towards the number of tiles.
+        assert_eq!(25, map.get_size());
+        assert_eq!(20, map.get_tile_count());
+
+        // Masked tiles are never neighbours.
+        assert_eq!(5, map.get_neighbours(&point::Point { x: 2, y: 1 }).len());
+        let neighbours: Vec<point::Point> = map.neighbours(&point::Point { x: 2, y: 1 }).collect();
+        assert_eq!(
+            vec![
+                point::Point { x: 1, y: 0 },
+                point::Point { x: 2, y: 0 },
+                point::Point { x: 3, y: 0 },
+                point::Point { x: 1, y: 1 },
+                point::Point { x: 3, y: 1 },
+            ],
+            neighbours
+        );
+
+        // Flipping or flagging a masked tile does nothing.
+        assert_eq!(0, map.flip(&point::Point { x: 2, y: 2 }));
+        map.flag(&point::Point { x: 2, y: 2 });
+        assert_eq!(1, map.get_mines_remaining());
+
+        // The wall stops the recursive flip from reaching the top half.
+        map.flip(&point::Point { x: 2, y: 4 });
+        assert_eq!(10, map.get_tiles_flipped());
+
+        // Print map into a buffer.
+        let mut output = Vec::new();
+        map.print(&mut output, false).unwrap();
+
+        // Masked tiles are printed as blank space.
+        let string = std::str::from_utf8(&output).unwrap();
+        assert_eq!("\n#####\n#####\n     \n00000\n00000\n", string);
+
+        // Flipping the rest of the existing tiles completes the map.
+        map.flip(&point::Point { x: 0, y: 1 });
+        map.flip(&point::Point { x: 4, y: 1 });
+        assert_eq!(map::Status::InProgress, *map.get_status());
+        map.flip(&point::Point { x: 2, y: 1 });
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_generate_puzzle_mask() {
+        // Generate a heart shaped puzzle.
+        let rows = [
+            " ## ## ", //
+            "#######", //
+            "#######", //
+            " ##### ", //
+            "  ###  ", //
+            "   #   ",
+        ];
+        let mask: Vec<bool> = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|c| c == '#'))
+            .collect();
+        let map = map::generate_map_with_mask(7, 6, mask, 8, point::Point { x: 3, y: 5 });
+
+        // Only the heart's tiles exist.
+        assert_eq!(27, map.get_tile_count());
+        assert_eq!(8, map.get_mines_remaining());
+
+        // Mines are only ever placed on existing tiles, never on the first click.
+        for i in 0..map.get_tiles().len() {
+            if map.get_tile(i).is_mine() {
+                assert!(map.tile_exists(i));
+            }
+        }
+        assert!(!map.get_tile(38).is_mine());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_generate_puzzle_mask_too_many_mines() {
+        // A 3x3 donut has 7 tiles besides the first click.
+        let mask: Vec<bool> = (0..9).map(|i| i != 4).collect();
+        map::generate_map_with_mask(3, 3, mask, 8, point::Point { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn test_toroidal_map() {
+        // Create a 4x4 map with wrapping edges and one mine in the corner.
+        let layout = map::Layout {
+            topology: point::Topology::Toroidal,
+            ..map::Layout::new(4, 4)
+        };
+        let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_layout_and_mines(layout, mines);
+
+        // Every tile has eight neighbours, even in the corners.
+        assert_eq!(8, map.get_neighbours(&point::Point { x: 0, y: 0 }).len());
+
+        // The mine is counted by tiles on the opposite edges.
+        assert_eq!(
+            1,
+            map.get_tile(point::Point { x: 3, y: 3 }.to_index(4)).value
+        );
+        assert_eq!(
+            1,
+            map.get_tile(point::Point { x: 0, y: 3 }.to_index(4)).value
+        );
+        assert_eq!(
+            0,
+            map.get_tile(point::Point { x: 2, y: 2 }.to_index(4)).value
+        );
+
+        // The recursive flip wraps around the edges to reach every other tile.
+        map.flip(&point::Point { x: 2, y: 2 });
+        assert_eq!(15, map.get_tiles_flipped());
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_hex_map() {
+        // Create a 4x4 hex map with one mine.
+        let layout = map::Layout {
+            grid: Arc::new(Hex),
+            ..map::Layout::new(4, 4)
+        };
+        let mines: HashSet<point::Point> = [point::Point { x: 1, y: 1 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_layout_and_mines(layout, mines);
+
+        // A tile in the middle of a hex map has six neighbours.
+        assert_eq!(6, map.get_neighbours(&point::Point { x: 1, y: 1 }).len());
+
+        // Flip a corner far from the mine.
+        map.flip(&point::Point { x: 3, y: 3 });
+
+        // Print map into a buffer.
+        let mut output = Vec::new();
+        map.print(&mut output, false).unwrap();
+
+        // Odd rows are shifted to line up the hexagons.
+        let string = std::str::from_utf8(&output).unwrap();
+        assert_eq!("\n# # 1 0\n 1 # 1 0\n0 1 1 0\n 0 0 0 0\n", string);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_toroidal_hex_odd_height() {
+        let layout = map::Layout {
+            topology: point::Topology::Toroidal,
+            grid: Arc::new(Hex),
+            ..map::Layout::new(4, 5)
+        };
+        map::generate_map_with_layout_and_mines(layout, HashSet::new());
+    }
+
+    #[test]
+    fn test_graph_map() {
+        // Create a map on the corners of a cube, with one mine.
+        let adjacency: Vec<Vec<usize>> = (0..8).map(|i| vec![i ^ 1, i ^ 2, i ^ 4]).collect();
+        let mines: HashSet<usize> = [0].iter().cloned().collect();
+        let mut map = map::generate_map_with_graph(adjacency, mines);
+
+        // Every corner touches three others.
+        assert_eq!(8, map.get_tile_count());
+        assert_eq!(&[0, 3, 5], map.get_adjacent(1));
+
+        // Only the corners touching the mine have a value.
+        assert_eq!(1, map.get_tile(1).value);
+        assert_eq!(1, map.get_tile(2).value);
+        assert_eq!(1, map.get_tile(4).value);
+        assert_eq!(0, map.get_tile(3).value);
+
+        // Flipping the far corner flips everything but the mine.
+        map.flip(&point::Point { x: 7, y: 0 });
+        assert_eq!(7, map.get_tiles_flipped());
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_3d_map() {
+        // Create a 3x3x3 map with a mine in the very centre.
+        let mines: HashSet<point::Point3> = [point::Point3 { x: 1, y: 1, z: 1 }]
+            .iter()
+            .cloned()
+            .collect();
+        let mut map = map::generate_3d_map_with_mines(3, 3, 3, mines);
+
+        // Every other tile touches the mine.
+        for i in 0..map.get_tiles().len() {
+            if !map.get_tile(i).is_mine() {
+                assert_eq!(1, map.get_tile(i).value);
+            }
+        }
+        assert_eq!(26, map.get_adjacent(13).len());
+
+        // Flip a corner on the top and bottom layers.
+        map.flip(&point::Point3 { x: 0, y: 0, z: 0 }.to_point(3));
+        map.flip(&point::Point3 { x: 2, y: 2, z: 2 }.to_point(3));
+
+        // Print map into a buffer.
+        let mut output = Vec::new();
+        map.print(&mut output, false).unwrap();
+
+        // Layers are printed one after another.
+        let string = std::str::from_utf8(&output).unwrap();
+        assert_eq!(
+            "\n1##\n###\n###\n\n###\n###\n###\n\n###\n###\n##1\n",
+            string
+        );
+    }
+
+    #[test]
+    fn test_multi_mine_map() {
+        // Create a 3x3 map where tiles hold up to 3 mines.
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 0, y: 0 }, 2),
+            (point::Point { x: 2, y: 0 }, 3),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map = map::generate_map_with_layout_and_mine_counts(Layout::new(3, 3), mines, 3);
+
+        // Values sum the mines on neighbouring tiles.
+        assert_eq!(5, map.get_tile(1).value);
+        assert_eq!(5, map.get_tile(4).value);
+        assert_eq!(2, map.get_tile(3).value);
+        assert_eq!(5, map.get_mines_remaining());
+
+        // Flags cycle through each count before unflagging.
+        let corner = point::Point { x: 0, y: 0 };
+        for flags in [1, 2, 3, 0, 1, 2].iter() {
+            map.flag(&corner);
+            assert_eq!(*flags, map.get_tile(0).flags);
+        }
+        assert_eq!(3, map.get_mines_remaining());
+
+        // The centre is satisfied once both corners carry the right number of flags.
+        assert!(!map.is_tile_satisfied(&point::Point { x: 1, y: 1 }));
+        map.flag(&point::Point { x: 2, y: 0 });
+        map.flag(&point::Point { x: 2, y: 0 });
+        map.flag(&point::Point { x: 2, y: 0 });
+        assert!(map.is_tile_satisfied(&point::Point { x: 1, y: 1 }));
+
+        // Flagging with no mines remaining unflags instead.
+        map.flag(&point::Point { x: 1, y: 2 });
+        assert_eq!(0, map.get_tile(7).flags);
+
+        // Flipping every tile without a mine completes the map.
+        map.flip(&point::Point { x: 1, y: 2 });
+        assert_eq!(6, map.get_tiles_flipped());
+        assert_eq!(map::Status::InProgress, *map.get_status());
+        map.flip(&point::Point { x: 1, y: 0 });
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_capacity_out_of_range() {
+        // A tile's mine count can't record more than 127 mines.
+        map::generate_map_with_capacity(Layout::new(4, 4), 4, 128, point::Point { x: 0, y: 0 });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_ratio_out_of_range() {
+        map::generate_map_with_negative_mines(
+            Layout::new(4, 4),
+            4,
+            1.5,
+            point::Point { x: 0, y: 0 },
+        );
+    }
+
+    #[test]
+    fn test_negative_mine_map() {
+        // Create a 4x3 map with a mine and a negative mine on the top row.
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 0, y: 0 }, 1),
+            (point::Point { x: 2, y: 0 }, -1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map = map::generate_map_with_layout_and_mine_counts(Layout::new(4, 3), mines, 1);
+        assert!(map.has_negative_mines());
+        assert_eq!(2, map.get_mines_remaining());
+
+        // Negative mines subtract from their neighbours, so the two mines cancel out.
+        assert_eq!(0, map.get_tile(1).value);
+        assert_eq!(-1, map.get_tile(3).value);
+
+        // Tiles next to mines that cancel out are flipped but not expanded.
+        map.flip(&point::Point { x: 3, y: 2 });
+        assert_eq!(8, map.get_tiles_flipped());
+        assert!(map.get_tile(5).flipped);
+        assert!(!map.get_tile(1).flipped);
+
+        // Negative flags toggle, and flagging a negatively flagged tile clears it.
+        let negative = point::Point { x: 2, y: 0 };
+        map.flag_negative(&negative);
+        assert_eq!(-1, map.get_tile(2).flags);
+        map.flag_negative(&negative);
+        assert_eq!(0, map.get_tile(2).flags);
+        map.flag_negative(&negative);
+        map.flag(&negative);
+        assert_eq!(0, map.get_tile(2).flags);
+        map.flag_negative(&negative);
+        map.flag(&point::Point { x: 0, y: 0 });
+        assert_eq!(0, map.get_mines_remaining());
+
+        // Satisfied tiles count negative flags against their value.
+        assert!(map.is_tile_satisfied(&point::Point { x: 1, y: 1 }));
+        assert!(map.is_tile_satisfied(&point::Point { x: 3, y: 1 }));
+
+        // Print map into a buffer.
+        let mut output = Vec::new();
+        map.print(&mut output, false).unwrap();
+
+        // Negative flags and values are printed with their own symbols, one character per tile.
+        let string = std::str::from_utf8(&output).unwrap();
+        assert_eq!("\n^#v#\n10aa\n0000\n", string);
+
+        // Flipping every tile without a mine completes the map.
+        map.flip(&point::Point { x: 1, y: 0 });
+        map.flip(&point::Point { x: 3, y: 0 });
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_neighbourhood_map() {
+        // Create a 5x5 map with one mine, where clues count knight moves.
+        let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+        let layout = Layout::with_neighbourhood(5, 5, Neighbourhood::knight());
+        let mut map = map::generate_map_with_layout_and_mines(layout, mines);
+
+        // Only tiles a knight move away from the mine count it.
+        assert_eq!(0, map.get_tile(1).value);
+        assert_eq!(0, map.get_tile(6).value);
+        assert_eq!(1, map.get_tile(7).value);
+        assert_eq!(1, map.get_tile(11).value);
+
+        // The recursive flip follows knight moves across the whole map.
+        map.flip(&point::Point { x: 4, y: 4 });
+        assert_eq!(24, map.get_tiles_flipped());
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_kind_mode() {
+        // Create a 3x1 map with a mine on the end.
+        let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(3, 1, mines.clone());
+        map.set_mode(map::Mode::Kind);
+        assert_eq!(map::Mode::Kind, map.get_mode());
+
+        // Guessing the mine moves it elsewhere.
+        map.flip(&point::Point { x: 2, y: 0 });
+        assert_ne!(map::Status::Failed, *map.get_status());
+        assert!(!map.get_tile(2).is_mine());
+        assert_eq!(
+            1,
+            map.get_tiles().iter().filter(|tile| tile.is_mine()).count()
+        );
+
+        // A tile that is certainly a mine stays one, as both clues around it only fit one mine.
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.reveal(&point::Point { x: 3, y: 0 });
+        map.set_mode(map::Mode::Kind);
+        map.flip(&point::Point { x: 2, y: 0 });
+        assert_eq!(map::Status::Failed, *map.get_status());
+
+        // The far end is certain to be safe once the clue next to the mine is revealed,
+        // so guessing the mine anyway isn't spared.
+        let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.set_mode(map::Mode::Kind);
+        map.flip(&point::Point { x: 0, y: 0 });
+        assert_eq!(map::Status::Failed, *map.get_status());
+    }
+
+    #[test]
+    fn test_kind_mode_chord() {
+        // Create a 3x1 map with a mine on the end and the wrong end flagged.
+        let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(3, 1, mines);
+        map.set_mode(map::Mode::Kind);
+        map.flip(&point::Point { x: 1, y: 0 });
+        map.flag(&point::Point { x: 0, y: 0 });
+
+        // Chording the clue guesses the other end, so the mine moves under the flag.
+        map.flip(&point::Point { x: 1, y: 0 });
+        assert_eq!(map::Status::Complete, *map.get_status());
+        assert!(map.get_tile(0).is_mine());
+        assert!(!map.get_tile(2).is_mine());
+    }
+
+    #[test]
+    fn test_cruel_mode() {
+        // Create a 5x1 map with a mine on the end.
+        let mines: HashSet<point::Point> = [point::Point { x: 4, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(5, 1, mines.clone());
+        map.set_mode(map::Mode::Cruel);
+
+        // Any guess finds a mine.
+        map.flip(&point::Point { x: 0, y: 0 });
+        assert_eq!(map::Status::Failed, *map.get_status());
+        assert!(map.get_tile(0).is_mine());
+
+        // A tile that is certainly safe stays safe, even when guessed.
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.set_mode(map::Mode::Cruel);
+        map.flip(&point::Point { x: 0, y: 0 });
+        assert_eq!(map::Status::InProgress, *map.get_status());
+
+        // Flags are ignored when moving mines, so a wrong flag doesn't protect a tile.
+        map.flag(&point::Point { x: 4, y: 0 });
+        map.flip(&point::Point { x: 3, y: 0 });
+        assert_eq!(map::Status::Failed, *map.get_status());
+        assert!(map.get_tile(3).is_mine());
+        assert!(!map.get_tile(4).is_mine());
+    }
+
+    #[test]
+    fn test_no_guess_map() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let click = point::Point { x: 8, y: 8 };
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..5 {
+            let map = map::generate_no_guess_map(16, 16, 40, click.clone(), &mut rng).unwrap();
+            assert_eq!(40, map.get_total_mines());
+            assert_eq!(0, map.get_tile(click.to_index(16)).value);
+
+            // Any random guesses would show up as a loss for some seed.
+            let mut start = map.clone();
+            start.flip(&click);
+            for seed in 0..5 {
+                let mut solved = start.clone();
+                let strategy = solver::GuessStrategy::LowestRisk;
+                solved.apply_moves(&solver::solve_with_rng(
+                    &start,
+                    strategy,
+                    &mut StdRng::seed_from_u64(seed),
+                ));
+                assert_eq!(map::Status::Complete, *solved.get_status());
+            }
+        }
+
+        // Every move of a solve is certain from what is revealed before it.
+        let map = map::generate_no_guess_map(16, 16, 40, click.clone(), &mut rng).unwrap();
+        let mut replay = map.clone();
+        replay.flip(&click);
+        let moves = solver::solve_with_rng(
+            &replay,
+            solver::GuessStrategy::LowestRisk,
+            &mut StdRng::seed_from_u64(0),
+        );
+        for play in &moves {
+            let probabilities = solver::mine_probabilities(&replay).unwrap();
+            let index: usize = play.position.to_index(16);
+            match play.move_type {
+                // Flipping a flipped tile only flips its neighbours once it is satisfied.
+                MoveType::Flip
+                    if replay.get_tile(index).flipped
+                        && !replay.is_tile_satisfied(&play.position) => {}
+                MoveType::Flip if replay.get_tile(index).flipped => {
+                    for neighbour in replay.get_adjacent(index) {
+                        if !replay.get_tile(*neighbour).flipped
+                            && !replay.get_tile(*neighbour).is_flagged()
+                        {
+                            assert!(probabilities[*neighbour] < 1e-9);
+                        }
+                    }
+                }
+                MoveType::Flip => assert!(probabilities[index] < 1e-9),
+                _ => assert!(probabilities[index] > 1.0 - 1e-9),
+            }
+            replay.apply_moves(&VecDeque::from(vec![play.clone()]));
+        }
+        assert_eq!(map::Status::Complete, *replay.get_status());
+
+        // No map can be solved without guessing if the mines fill every other tile.
+        let click = point::Point { x: 0, y: 0 };
+        assert!(map::generate_no_guess_map(4, 4, 15, click, &mut rng).is_none());
+    }
 }
@@ -2,8 +2,17 @@
 
 use std::collections::HashSet;
 
+/// Represents how the edges of a puzzle connect.
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+pub enum Topology {
+    /// Tiles on the edges have fewer neighbours.
+    Bounded,
+    /// Edges wrap around, so every tile has the same number of neighbours.
+    Toroidal,
+}
+
 /// Represents a 2d point.
-#[derive(Eq, Hash, PartialEq, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Default)]
 pub struct Point {
     pub x: u16,
     pub y: u16,
@@ -62,67 +71,277 @@ pub fn from_index(index: usize, width: u16) -> Point {
 /// );
 /// ```
 pub fn get_neighbours(position: &Point, width: u16, height: u16) -> HashSet<Point> {
-    let mut neighbours = HashSet::new();
-
-    let u: bool = position.y > 0;
-    let d: bool = position.y < (height - 1);
-    let l: bool = position.x > 0;
-    let r: bool = position.x < (width - 1);
-
-    if u {
-        neighbours.insert(Point {
-            x: position.x,
-            y: position.y - 1,
-        });
+    neighbours(position, width, height, Topology::Bounded).collect()
+}
+
+/// Get an array of points representing adjacent tiles given how the edges of the puzzle connect.
+///
+/// ```
+/// use casspir::point::{self, Topology};
+/// use std::collections::HashSet;
+///
+/// let expected: HashSet<point::Point> = [
+///     point::Point { x: 1, y: 0 },
+///     point::Point { x: 0, y: 1 },
+///     point::Point { x: 1, y: 1 },
+///     point::Point { x: 5, y: 0 },
+///     point::Point { x: 5, y: 1 },
+///     point::Point { x: 0, y: 9 },
+///     point::Point { x: 1, y: 9 },
+///     point::Point { x: 5, y: 9 },
+/// ].iter().cloned().collect();
+/// assert_eq!(
+///     point::get_neighbours_with_topology(&point::Point { x: 0, y: 0 }, 6, 10, Topology::Toroidal),
+///     expected
+/// );
+/// ```
+pub fn get_neighbours_with_topology(
+    position: &Point,
+    width: u16,
+    height: u16,
+    topology: Topology,
+) -> HashSet<Point> {
+    neighbours(position, width, height, topology).collect()
+}
+
+/// An iterator over the tiles adjacent to a point, held in place without allocating.
+/// Produced by `neighbours`.
+#[derive(Clone, Debug)]
+pub struct Neighbours {
+    points: [Point; 8],
+    len: usize,
+    next: usize,
+}
+
+impl Iterator for Neighbours {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.next == self.len {
+            return None;
+        }
+        self.next += 1;
+        Some(self.points[self.next - 1].clone())
     }
 
-    if d {
-        neighbours.insert(Point {
-            x: position.x,
-            y: position.y + 1,
-        });
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.next, Some(self.len - self.next))
     }
+}
+
+impl ExactSizeIterator for Neighbours {}
+
+/// Iterate over the tiles adjacent to `position` given how the edges of the puzzle connect,
+/// row by row from the top left. Unlike `get_neighbours_with_topology` this doesn't allocate,
+/// and a tile reached by wrapping around more than one edge is only produced once.
+///
+/// ```
+/// use casspir::point::{self, Point, Topology};
+///
+/// let neighbours: Vec<Point> = point::neighbours(&Point { x: 0, y: 0 }, 6, 10, Topology::Bounded).collect();
+/// assert_eq!(vec![Point { x: 1, y: 0 }, Point { x: 0, y: 1 }, Point { x: 1, y: 1 }], neighbours);
+/// assert_eq!(8, point::neighbours(&Point { x: 0, y: 0 }, 6, 10, Topology::Toroidal).len());
+/// ```
+pub fn neighbours(position: &Point, width: u16, height: u16, topology: Topology) -> Neighbours {
+    let mut neighbours = Neighbours {
+        points: Default::default(),
+        len: 0,
+        next: 0,
+    };
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let mut x: i32 = position.x as i32 + dx;
+            let mut y: i32 = position.y as i32 + dy;
+
+            if topology == Topology::Toroidal {
+                x = x.rem_euclid(width as i32);
+                y = y.rem_euclid(height as i32);
+            } else if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                continue;
+            }
+
+            let neighbour = Point {
+                x: x as u16,
+                y: y as u16,
+            };
+            // On narrow puzzles an offset can wrap back onto the tile itself or another neighbour.
+            if neighbour != *position && !neighbours.points[..neighbours.len].contains(&neighbour) {
+                neighbours.points[neighbours.len] = neighbour;
+                neighbours.len += 1;
+            }
+        }
+    }
+
+    neighbours
+}
+
+/// Represents a 3d point.
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Default)]
+pub struct Point3 {
+    pub x: u16,
+    pub y: u16,
+    pub z: u16,
+}
 
-    if l {
-        neighbours.insert(Point {
-            x: position.x - 1,
-            y: position.y,
-        });
+impl Point3 {
+    /// Create an array index from this point and the given `width` and `height`.
+    ///
+    /// ```
+    /// use casspir::point;
+    /// let point = point::Point3 { x: 3, y: 7, z: 2 };
+    /// assert_eq!(point.to_index(10, 8), 233);
+    /// ```
+    pub fn to_index(&self, width: u16, height: u16) -> usize {
+        self.to_point(height).to_index(width)
     }
 
-    if r {
-        neighbours.insert(Point {
-            x: position.x + 1,
-            y: position.y,
-        });
+    /// Flatten this point onto a 2d point by stacking layers of the given `height` vertically.
+    ///
+    /// ```
+    /// use casspir::point;
+    /// let point = point::Point3 { x: 3, y: 7, z: 2 };
+    /// assert_eq!(point.to_point(8), point::Point { x: 3, y: 23 });
+    /// ```
+    pub fn to_point(&self, height: u16) -> Point {
+        let y: u32 = self.z as u32 * height as u32 + self.y as u32;
+        if y > u16::MAX as u32 {
+            panic!("Unsupported puzzle dimensions.");
+        }
+        Point {
+            x: self.x,
+            y: y as u16,
+        }
     }
 
-    if u && l {
-        neighbours.insert(Point {
-            x: position.x - 1,
-            y: position.y - 1,
-        });
+    /// Create a `Point3` from a 2d `point` made by stacking layers of the given `height` vertically.
+    ///
+    /// ```
+    /// use casspir::point;
+    /// let point = point::Point3::from_point(&point::Point { x: 3, y: 23 }, 8);
+    /// assert_eq!(point, point::Point3 { x: 3, y: 7, z: 2 });
+    /// ```
+    pub fn from_point(point: &Point, height: u16) -> Point3 {
+        Point3 {
+            x: point.x,
+            y: point.y % height,
+            z: point.y / height,
+        }
     }
+}
 
-    if u && r {
-        neighbours.insert(Point {
-            x: position.x + 1,
-            y: position.y - 1,
-        });
+/// Get an array of points representing adjacent tiles in 3d given how the edges of the puzzle connect.
+///
+/// ```
+/// use casspir::point::{self, Topology};
+///
+/// let centre = point::Point3 { x: 1, y: 1, z: 1 };
+/// assert_eq!(26, point::get_neighbours_3d(&centre, 3, 3, 3, Topology::Bounded).len());
+///
+/// let corner = point::Point3 { x: 0, y: 0, z: 0 };
+/// assert_eq!(7, point::get_neighbours_3d(&corner, 3, 3, 3, Topology::Bounded).len());
+/// assert_eq!(26, point::get_neighbours_3d(&corner, 3, 3, 3, Topology::Toroidal).len());
+/// ```
+pub fn get_neighbours_3d(
+    position: &Point3,
+    width: u16,
+    height: u16,
+    depth: u16,
+    topology: Topology,
+) -> HashSet<Point3> {
+    neighbours_3d(position, width, height, depth, topology).collect()
+}
+
+/// An iterator over the tiles adjacent to a 3d point, held in place without allocating.
+/// Produced by `neighbours_3d`.
+#[derive(Clone, Debug)]
+pub struct Neighbours3 {
+    points: [Point3; 26],
+    len: usize,
+    next: usize,
+}
+
+impl Iterator for Neighbours3 {
+    type Item = Point3;
+
+    fn next(&mut self) -> Option<Point3> {
+        if self.next == self.len {
+            return None;
+        }
+        self.next += 1;
+        Some(self.points[self.next - 1].clone())
     }
 
-    if d && l {
-        neighbours.insert(Point {
-            x: position.x - 1,
-            y: position.y + 1,
-        });
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.next, Some(self.len - self.next))
     }
+}
+
+impl ExactSizeIterator for Neighbours3 {}
+
+/// Iterate over the tiles adjacent to `position` in 3d given how the edges of the puzzle connect,
+/// layer by layer from the top left. Unlike `get_neighbours_3d` this doesn't allocate,
+/// and a tile reached by wrapping around more than one edge is only produced once.
+///
+/// ```
+/// use casspir::point::{self, Point3, Topology};
+///
+/// let corner = Point3 { x: 0, y: 0, z: 0 };
+/// assert_eq!(7, point::neighbours_3d(&corner, 3, 3, 3, Topology::Bounded).len());
+/// assert_eq!(
+///     Some(Point3 { x: 1, y: 0, z: 0 }),
+///     point::neighbours_3d(&corner, 3, 3, 3, Topology::Bounded).next()
+/// );
+/// assert_eq!(26, point::neighbours_3d(&corner, 3, 3, 3, Topology::Toroidal).len());
+/// ```
+pub fn neighbours_3d(
+    position: &Point3,
+    width: u16,
+    height: u16,
+    depth: u16,
+    topology: Topology,
+) -> Neighbours3 {
+    let mut neighbours = Neighbours3 {
+        points: Default::default(),
+        len: 0,
+        next: 0,
+    };
+
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let mut x: i32 = position.x as i32 + dx;
+                let mut y: i32 = position.y as i32 + dy;
+                let mut z: i32 = position.z as i32 + dz;
+
+                if topology == Topology::Toroidal {
+                    x = x.rem_euclid(width as i32);
+                    y = y.rem_euclid(height as i32);
+                    z = z.rem_euclid(depth as i32);
+                } else if x < 0
+                    || y < 0
+                    || z < 0
+                    || x >= width as i32
+                    || y >= height as i32
+                    || z >= depth as i32
+                {
+                    continue;
+                }
 
-    if d && r {
-        neighbours.insert(Point {
-            x: position.x + 1,
-            y: position.y + 1,
-        });
+                let neighbour = Point3 {
+                    x: x as u16,
+                    y: y as u16,
+                    z: z as u16,
+                };
+                // On small puzzles an offset can wrap back onto the tile itself or another neighbour.
+                if neighbour != *position
+                    && !neighbours.points[..neighbours.len].contains(&neighbour)
+                {
+                    neighbours.points[neighbours.len] = neighbour;
+                    neighbours.len += 1;
+                }
+            }
+        }
     }
 
     neighbours
@@ -0,0 +1,147 @@
+//! This module contains static logic puzzles, where some clues are revealed up front
+//! and every mine has to be deduced without flipping any other tiles.
+
+use crate::map::{self, Layout, Map};
+use crate::point::{self, Point};
+use crate::solver;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::thread_rng;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::iter::FromIterator;
+
+/// A map along with the tiles whose values are revealed as clues.
+#[derive(Clone)]
+pub struct Puzzle {
+    /// The map holding the solution.
+    solution: Map,
+    /// The positions of the revealed clues.
+    clues: HashSet<Point>,
+}
+
+impl Puzzle {
+    /// Create a puzzle from a `solution` map that reveals the given `clues`.
+    pub fn new(solution: Map, clues: HashSet<Point>) -> Puzzle {
+        for clue in &clues {
+            let index: usize = clue.to_index(solution.get_width());
+            if !solution.tile_exists(index) {
+                panic!("Cannot reveal a tile that doesn't exist.");
+            }
+            if solution.get_tile(index).is_mine() {
+                panic!("Cannot reveal a mine as a clue.");
+            }
+        }
+
+        Puzzle { solution, clues }
+    }
+
+    pub fn get_solution(&self) -> &Map {
+        &self.solution
+    }
+    pub fn get_clues(&self) -> &HashSet<Point> {
+        &self.clues
+    }
+
+    /// Get the map as presented to the player, with only the clues flipped.
+    pub fn to_map(&self) -> Map {
+        let mut map = self.solution.clone();
+        for clue in &self.clues {
+            map.reveal(clue);
+        }
+        map
+    }
+
+    /// Checks if exactly one layout of mines fits the clues and the total number of mines.
+    pub fn is_unique(&self) -> bool {
+        solver::count_solutions(&self.to_map(), 2) == 1
+    }
+
+    /// Print the puzzle as presented to the player.
+    pub fn print(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.to_map().print(writer, false)
+    }
+}
+
+/// Generate a uniquely solvable puzzle with the given `layout` and `total` number of mines,
+/// revealing as few clues as the greedy removal in `generate_puzzle_from_map` allows.
+///
+/// ```
+/// use casspir::{map, puzzle};
+/// let puzzle = puzzle::generate_puzzle(map::Layout::new(6, 6), 6);
+/// assert!(puzzle.is_unique());
+/// assert!(puzzle.get_clues().len() < 30);
+/// ```
+pub fn generate_puzzle(layout: Layout, total: u32) -> Puzzle {
+    let mines: Vec<Point> = (0..layout.mask.len())
+        .filter(|i| layout.mask[*i])
+        .map(|i| point::from_index(i, layout.width))
+        .choose_multiple(&mut thread_rng(), total as usize);
+    let solution = map::generate_map_with_layout_and_mines(layout, HashSet::from_iter(mines));
+    generate_puzzle_from_map(solution)
+}
+
+/// Generate a puzzle from a `solution` map by revealing every tile without a mine,
+/// then removing clues in a random order as long as the puzzle stays uniquely solvable.
+pub fn generate_puzzle_from_map(solution: Map) -> Puzzle {
+    let mut clues: Vec<Point> = (0..solution.get_tiles().len())
+        .filter(|i| solution.tile_exists(*i) && !solution.get_tile(*i).is_mine())
+        .map(|i| point::from_index(i, solution.get_width()))
+        .collect();
+    clues.shuffle(&mut thread_rng());
+
+    let mut puzzle = Puzzle::new(solution, HashSet::from_iter(clues.iter().cloned()));
+    for clue in clues {
+        puzzle.clues.remove(&clue);
+        if !puzzle.is_unique() {
+            puzzle.clues.insert(clue);
+        }
+    }
+
+    puzzle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_puzzle_uniqueness() {
+        // Create a 3x1 map with a mine on the end.
+        let mines: HashSet<Point> = [Point { x: 2, y: 0 }].iter().cloned().collect();
+        let solution = map::generate_map_with_mines(3, 1, mines);
+
+        // The middle clue alone can't tell which end the mine is on.
+        let clues: HashSet<Point> = [Point { x: 1, y: 0 }].iter().cloned().collect();
+        let puzzle = Puzzle::new(solution.clone(), clues);
+        assert!(!puzzle.is_unique());
+
+        // Revealing the other end settles it.
+        let clues: HashSet<Point> = [Point { x: 0, y: 0 }].iter().cloned().collect();
+        let puzzle = Puzzle::new(solution, clues);
+        assert!(puzzle.is_unique());
+
+        // Only the clues are shown to the player.
+        let mut output = Vec::new();
+        puzzle.print(&mut output).unwrap();
+        let string = std::str::from_utf8(&output).unwrap();
+        assert_eq!("\n0##\n", string);
+    }
+
+    #[test]
+    fn test_generate_puzzle() {
+        let puzzle = generate_puzzle(Layout::new(8, 8), 10);
+        let map = puzzle.to_map();
+
+        // Only clues are flipped, and none of them are mines.
+        assert_eq!(puzzle.get_clues().len() as u32, map.get_tiles_flipped());
+        assert_eq!(map::Status::InProgress, *map.get_status());
+
+        // Every remaining clue is needed to keep the puzzle unique.
+        assert!(puzzle.is_unique());
+        for clue in puzzle.get_clues() {
+            let mut clues = puzzle.get_clues().clone();
+            clues.remove(clue);
+            assert!(!Puzzle::new(puzzle.get_solution().clone(), clues).is_unique());
+        }
+    }
+}
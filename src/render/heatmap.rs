@@ -0,0 +1,221 @@
+//! Drawing the chance of a mine on each tile as text, to show what the solver thinks.
+
+use super::{
+    tile_glyph, write_colored, write_column_labels, write_row_label, Color, RenderOptions,
+};
+use crate::map::Map;
+use crate::solver;
+use std::io::{self, Write};
+
+/// The ASCII shades from no chance of a mine to a certain mine.
+const ASCII_SHADES: [char; 9] = ['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+/// The Unicode shades from no chance of a mine to a certain mine.
+const UNICODE_SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// The colour of a chance of a mine, from green for safe to magenta for certain.
+pub fn heat_color(probability: f64) -> Color {
+    if probability <= 0.0 {
+        Color::Green
+    } else if probability < 0.2 {
+        Color::Blue
+    } else if probability < 0.5 {
+        Color::Yellow
+    } else if probability < 1.0 {
+        Color::Red
+    } else {
+        Color::Magenta
+    }
+}
+
+/// Format a chance as a whole percentage, never rounding to certainty.
+fn percentage(probability: f64) -> String {
+    let rounded: f64 = (probability * 100.0).round();
+    if rounded == 0.0 && probability > 0.0 {
+        "<1%".into()
+    } else if rounded == 100.0 && probability < 1.0 {
+        ">99%".into()
+    } else {
+        format!("{}%", rounded)
+    }
+}
+
+/// Pick one of `count` shades for a chance, keeping the first and last for certainty like `percentage`.
+fn shade_index(probability: f64, count: usize) -> usize {
+    if probability <= 0.0 {
+        0
+    } else if probability >= 1.0 {
+        count - 1
+    } else {
+        ((probability * (count - 1) as f64).round() as usize).clamp(1, count - 2)
+    }
+}
+
+/// Write rows of tiles, drawing each with `cell` and labelling them if asked.
+fn write_grid(
+    map: &Map,
+    writer: &mut dyn Write,
+    options: &RenderOptions,
+    spacing: usize,
+    cell: &mut dyn FnMut(&mut dyn Write, usize) -> io::Result<()>,
+) -> io::Result<()> {
+    let (width, height) = (map.get_width(), map.get_height());
+    if options.axes {
+        write_column_labels(writer, width, height, spacing, options)?;
+    }
+    for y in 0..height {
+        if options.axes {
+            write_row_label(writer, y, height, options)?;
+        }
+        for x in 0..width {
+            cell(writer, y as usize * width as usize + x as usize)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write the chance of a mine on each hidden tile as a percentage, with other tiles drawn as usual.
+/// Hidden tiles without a chance, such as those outside any group, are marked with `?`.
+pub fn write_probability_grid(
+    map: &Map,
+    probabilities: &[Option<f64>],
+    writer: &mut dyn Write,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    write_grid(map, writer, options, 5, &mut |writer, index| {
+        let glyph = tile_glyph(map, index, options);
+        let tile = map.get_tile(index);
+        if !map.tile_exists(index) || tile.flipped || tile.is_flagged() || options.revealed {
+            write!(writer, "    ")?;
+            return write_colored(writer, glyph.symbol, glyph.color, glyph.background, options);
+        }
+        match probabilities[index] {
+            Some(probability) => write_colored(
+                writer,
+                format!("{:>5}", percentage(probability)),
+                Some(heat_color(probability)),
+                None,
+                options,
+            ),
+            None => write_colored(writer, "    ?", Some(Color::DarkGrey), None, options),
+        }
+    })
+}
+
+/// Write the chance of a mine on each hidden tile as a shade, darker for more likely mines,
+/// followed by a legend. Flipped tiles are left blank and flags drawn as usual.
+/// Hidden tiles without a chance, such as those outside any group, are marked with `?`.
+pub fn write_shaded_heatmap(
+    map: &Map,
+    probabilities: &[Option<f64>],
+    writer: &mut dyn Write,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let shades: &[char] = if options.unicode {
+        &UNICODE_SHADES
+    } else {
+        &ASCII_SHADES
+    };
+    let shade = |probability: f64| shades[shade_index(probability, shades.len())];
+
+    write_grid(map, writer, options, 2, &mut |writer, index| {
+        let tile = map.get_tile(index);
+        write!(writer, " ")?;
+        if !map.tile_exists(index) || tile.flipped {
+            return write!(writer, " ");
+        }
+        if tile.is_flagged() {
+            let glyph = tile_glyph(map, index, options);
+            return write_colored(writer, glyph.symbol, glyph.color, glyph.background, options);
+        }
+        match probabilities[index] {
+            Some(probability) => write_colored(
+                writer,
+                shade(probability),
+                Some(heat_color(probability)),
+                None,
+                options,
+            ),
+            None => write_colored(writer, '?', Some(Color::DarkGrey), None, options),
+        }
+    })?;
+    writeln!(
+        writer,
+        "{} 0% to {} 100%, ? outside any group",
+        shades[0],
+        shades[shades.len() - 1]
+    )
+}
+
+/// Write the chance of a mine on each hidden tile as the solver sees it, from
+/// `solver::group_probabilities`, first as percentages and then as shades.
+///
+/// ```
+/// use casspir::{map, point, render};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 29, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(30, 1, mines);
+/// map.reveal(&point::Point { x: 28, y: 0 });
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// render::heatmap::render_heatmap(&map, &mut out, &render::RenderOptions::plain()).unwrap();
+/// let out = String::from_utf8(out).unwrap();
+/// assert!(out.starts_with("    ?"));
+/// assert!(out.contains("  50%    1  50%\n"));
+/// ```
+pub fn render_heatmap(
+    map: &Map,
+    writer: &mut dyn Write,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let probabilities: Vec<Option<f64>> = solver::group_probabilities(map);
+    write_probability_grid(map, &probabilities, writer, options)?;
+    writeln!(writer)?;
+    write_shaded_heatmap(map, &probabilities, writer, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::point::Point;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_heatmap() {
+        // A 5x1 map with a mine on the end, a clue beside it and a flag.
+        let mines: HashSet<Point> = [Point { x: 4, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&Point { x: 3, y: 0 });
+        map.flag(&Point { x: 0, y: 0 });
+        let probabilities = vec![None, None, Some(0.25), None, Some(1.0)];
+
+        let mut out: Vec<u8> = Vec::new();
+        let options = RenderOptions::plain();
+        write_probability_grid(&map, &probabilities, &mut out, &options).unwrap();
+        write_shaded_heatmap(&map, &probabilities, &mut out, &options).unwrap();
+        assert_eq!(
+            "    ^    ?  25%    1 100%\n ^ ? -   @\n. 0% to @ 100%, ? outside any group\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_percentage() {
+        assert_eq!("0%", percentage(0.0));
+        assert_eq!("<1%", percentage(0.001));
+        assert_eq!("33%", percentage(1.0 / 3.0));
+        assert_eq!(">99%", percentage(0.999));
+        assert_eq!("100%", percentage(1.0));
+    }
+
+    #[test]
+    fn test_shade_index() {
+        assert_eq!(0, shade_index(0.0, 9));
+        assert_eq!(1, shade_index(0.01, 9));
+        assert_eq!(4, shade_index(0.5, 9));
+        assert_eq!(7, shade_index(0.99, 9));
+        assert_eq!(8, shade_index(1.0, 9));
+    }
+}
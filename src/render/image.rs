@@ -0,0 +1,791 @@
+//! Drawing maps as SVG and PNG images.
+//!
+//! Every image is built as a list of simple shapes, which are then written out as SVG or,
+//! with the `png` feature, rasterised with tiny-skia. Numbers are drawn as strokes rather
+//! than text, so images look the same everywhere without needing any fonts.
+
+use super::{tile_glyph, RenderOptions};
+use crate::map::{Map, Status};
+use crate::solver::{self, Move};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// A colour given by its red, green and blue components.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// The colours an image is drawn with.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Theme {
+    /// Behind the tiles, showing as lines between them.
+    pub background: Rgb,
+    pub hidden: Rgb,
+    pub flipped: Rgb,
+    pub flag: Rgb,
+    pub mine: Rgb,
+    /// Behind the mine that was flipped.
+    pub exploded: Rgb,
+    /// The cross over a wrong flag.
+    pub wrong: Rgb,
+    /// The colour of each value from 1 to 8, larger values use the last.
+    pub numbers: [Rgb; 8],
+    /// Laid over hidden tiles, more strongly the more likely they hold a mine.
+    pub heatmap: Rgb,
+}
+
+impl Theme {
+    /// Grey tiles with the classic colour for each number.
+    pub fn classic() -> Theme {
+        Theme {
+            background: Rgb(0x80, 0x80, 0x80),
+            hidden: Rgb(0xbd, 0xbd, 0xbd),
+            flipped: Rgb(0xe8, 0xe8, 0xe8),
+            flag: Rgb(0xff, 0x00, 0x00),
+            mine: Rgb(0x00, 0x00, 0x00),
+            exploded: Rgb(0xff, 0x00, 0x00),
+            wrong: Rgb(0xff, 0x00, 0x00),
+            numbers: [
+                Rgb(0x00, 0x00, 0xff),
+                Rgb(0x00, 0x80, 0x00),
+                Rgb(0xff, 0x00, 0x00),
+                Rgb(0x00, 0x00, 0x80),
+                Rgb(0x80, 0x00, 0x00),
+                Rgb(0x00, 0x80, 0x80),
+                Rgb(0x00, 0x00, 0x00),
+                Rgb(0x80, 0x80, 0x80),
+            ],
+            heatmap: Rgb(0xff, 0x6a, 0x00),
+        }
+    }
+
+    /// Light numbers on dark tiles.
+    pub fn dark() -> Theme {
+        Theme {
+            background: Rgb(0x16, 0x1b, 0x22),
+            hidden: Rgb(0x3d, 0x44, 0x4d),
+            flipped: Rgb(0x22, 0x27, 0x2e),
+            flag: Rgb(0xff, 0x7b, 0x72),
+            mine: Rgb(0xe6, 0xed, 0xf3),
+            exploded: Rgb(0xda, 0x36, 0x33),
+            wrong: Rgb(0xff, 0x7b, 0x72),
+            numbers: [
+                Rgb(0x6c, 0xb6, 0xff),
+                Rgb(0x7e, 0xe7, 0x87),
+                Rgb(0xff, 0x7b, 0x72),
+                Rgb(0xd2, 0xa8, 0xff),
+                Rgb(0xff, 0xa6, 0x57),
+                Rgb(0x39, 0xc5, 0xcf),
+                Rgb(0xe6, 0xed, 0xf3),
+                Rgb(0x8b, 0x94, 0x9e),
+            ],
+            heatmap: Rgb(0xff, 0x7b, 0x72),
+        }
+    }
+}
+
+/// Options for drawing a map as an image.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ImageOptions {
+    /// The width and height of each tile in pixels.
+    pub tile_size: u32,
+    pub theme: Theme,
+    /// Show what is under every tile.
+    pub revealed: bool,
+    /// Shade each hidden tile by the chance that it holds a mine, see `solver::mine_probabilities`.
+    /// Nothing is shaded if the flags rule out every mine layout.
+    pub heatmap: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> ImageOptions {
+        ImageOptions {
+            tile_size: 32,
+            theme: Theme::classic(),
+            revealed: false,
+            heatmap: false,
+        }
+    }
+}
+
+/// The file formats an image can be written in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ImageFormat {
+    Svg,
+    /// Only available with the `png` feature.
+    #[cfg(feature = "png")]
+    Png,
+}
+
+/// A shape to draw, in pixels.
+#[derive(Clone, Debug)]
+enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        fill: Rgb,
+        opacity: f32,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+        fill: Rgb,
+    },
+    Polygon {
+        points: Vec<(f32, f32)>,
+        fill: Rgb,
+    },
+    /// Connected lines with rounded ends.
+    Line {
+        points: Vec<(f32, f32)>,
+        stroke: Rgb,
+        width: f32,
+    },
+}
+
+/// The strokes of each digit, on a grid 4 wide and 6 tall.
+fn digit_strokes(digit: u32) -> Vec<Vec<(f32, f32)>> {
+    let strokes: &[&[(f32, f32)]] = match digit {
+        0 => &[&[
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (1.0, 6.0),
+            (0.0, 5.0),
+            (0.0, 1.0),
+            (1.0, 0.0),
+        ]],
+        1 => &[
+            &[(1.0, 1.0), (2.0, 0.0), (2.0, 6.0)],
+            &[(1.0, 6.0), (3.0, 6.0)],
+        ],
+        2 => &[&[
+            (0.0, 1.0),
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 2.0),
+            (0.0, 6.0),
+            (4.0, 6.0),
+        ]],
+        3 => &[&[
+            (0.0, 0.0),
+            (4.0, 0.0),
+            (2.0, 2.5),
+            (3.0, 2.5),
+            (4.0, 3.5),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (1.0, 6.0),
+            (0.0, 5.0),
+        ]],
+        4 => &[&[(3.0, 6.0), (3.0, 0.0), (0.0, 4.0), (4.0, 4.0)]],
+        5 => &[&[
+            (4.0, 0.0),
+            (0.0, 0.0),
+            (0.0, 2.5),
+            (3.0, 2.5),
+            (4.0, 3.5),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (0.0, 6.0),
+        ]],
+        6 => &[&[
+            (3.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+            (0.0, 5.0),
+            (1.0, 6.0),
+            (3.0, 6.0),
+            (4.0, 5.0),
+            (4.0, 3.5),
+            (3.0, 2.5),
+            (0.0, 2.5),
+        ]],
+        7 => &[&[(0.0, 0.0), (4.0, 0.0), (1.5, 6.0)]],
+        8 => &[&[
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 2.0),
+            (3.0, 3.0),
+            (1.0, 3.0),
+            (0.0, 4.0),
+            (0.0, 5.0),
+            (1.0, 6.0),
+            (3.0, 6.0),
+            (4.0, 5.0),
+            (4.0, 4.0),
+            (3.0, 3.0),
+            (1.0, 3.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+            (1.0, 0.0),
+        ]],
+        _ => &[&[
+            (4.0, 2.5),
+            (3.0, 3.5),
+            (1.0, 3.5),
+            (0.0, 2.5),
+            (0.0, 1.0),
+            (1.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 1.0),
+            (4.0, 5.0),
+            (3.0, 6.0),
+            (1.0, 6.0),
+        ]],
+    };
+    strokes.iter().map(|stroke| stroke.to_vec()).collect()
+}
+
+/// Draws shapes within the tile at a position, using coordinates from 0 to 16 across the tile.
+struct TilePainter<'a> {
+    shapes: &'a mut Vec<Shape>,
+    x: f32,
+    y: f32,
+    size: f32,
+}
+
+impl TilePainter<'_> {
+    fn point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (self.x + x * self.size / 16.0, self.y + y * self.size / 16.0)
+    }
+
+    fn line(&mut self, points: &[(f32, f32)], stroke: Rgb, width: f32) {
+        let points = points.iter().map(|point| self.point(*point)).collect();
+        let width: f32 = width * self.size / 16.0;
+        self.shapes.push(Shape::Line {
+            points,
+            stroke,
+            width,
+        });
+    }
+
+    fn polygon(&mut self, points: &[(f32, f32)], fill: Rgb) {
+        let points = points.iter().map(|point| self.point(*point)).collect();
+        self.shapes.push(Shape::Polygon { points, fill });
+    }
+
+    fn circle(&mut self, centre: (f32, f32), radius: f32, fill: Rgb) {
+        let (x, y) = self.point(centre);
+        let radius: f32 = radius * self.size / 16.0;
+        self.shapes.push(Shape::Circle { x, y, radius, fill });
+    }
+
+    /// Draw a flag, hollow for negative flags.
+    fn flag(&mut self, theme: &Theme, negative: bool) {
+        self.line(&[(8.5, 3.0), (8.5, 12.0)], theme.mine, 1.2);
+        self.polygon(&[(8.5, 2.5), (8.5, 8.5), (3.5, 5.5)], theme.flag);
+        if negative {
+            self.polygon(&[(7.5, 4.5), (7.5, 6.5), (5.8, 5.5)], theme.hidden);
+        }
+        self.polygon(
+            &[(5.0, 12.0), (12.0, 12.0), (12.0, 13.5), (5.0, 13.5)],
+            theme.mine,
+        );
+    }
+
+    /// Draw a mine, hollow for negative mines.
+    fn mine(&mut self, theme: &Theme, background: Rgb, negative: bool) {
+        for spike in [
+            [(1.5, 8.0), (14.5, 8.0)],
+            [(8.0, 1.5), (8.0, 14.5)],
+            [(3.5, 3.5), (12.5, 12.5)],
+            [(12.5, 3.5), (3.5, 12.5)],
+        ] {
+            self.line(&spike, theme.mine, 1.2);
+        }
+        self.circle((8.0, 8.0), 4.5, theme.mine);
+        if negative {
+            self.circle((8.0, 8.0), 2.5, background);
+        } else {
+            self.circle((6.5, 6.5), 1.2, Rgb(0xff, 0xff, 0xff));
+        }
+    }
+
+    /// Draw a number centred in the tile.
+    fn number(&mut self, theme: &Theme, value: i16) {
+        let color: Rgb = theme.numbers[(value.unsigned_abs() as usize).clamp(1, 8) - 1];
+        let digits: Vec<u32> = value
+            .unsigned_abs()
+            .to_string()
+            .chars()
+            .filter_map(|digit| digit.to_digit(10))
+            .collect();
+
+        // Each digit is 4 wide with a gap of 2 between them, and a minus sign goes in front.
+        let scale: f32 = 1.4;
+        let count: f32 = digits.len() as f32 + if value < 0 { 1.0 } else { 0.0 };
+        let left: f32 = 8.0 - (count * 6.0 - 2.0) * scale / 2.0;
+        let top: f32 = 8.0 - 3.0 * scale;
+        let mut offset: f32 = left;
+        if value < 0 {
+            self.line(&[(offset, 8.0), (offset + 4.0 * scale, 8.0)], color, 1.8);
+            offset += 6.0 * scale;
+        }
+        for digit in digits {
+            for stroke in digit_strokes(digit) {
+                let points: Vec<(f32, f32)> = stroke
+                    .iter()
+                    .map(|(x, y)| (offset + x * scale, top + y * scale))
+                    .collect();
+                self.line(&points, color, 1.8);
+            }
+            offset += 6.0 * scale;
+        }
+    }
+}
+
+/// Build the shapes making up an image of the map, along with its width and height.
+fn build_scene(map: &Map, options: &ImageOptions) -> (u32, u32, Vec<Shape>) {
+    let size: f32 = options.tile_size as f32;
+    let (width, height) = (
+        map.get_width() as u32 * options.tile_size,
+        map.get_height() as u32 * options.tile_size,
+    );
+    let theme: &Theme = &options.theme;
+    let mut shapes: Vec<Shape> = vec![Shape::Rect {
+        x: 0.0,
+        y: 0.0,
+        width: width as f32,
+        height: height as f32,
+        fill: theme.background,
+        opacity: 1.0,
+    }];
+
+    // Probabilities only mean something while the game is being played,
+    // and there are none to draw when the flags rule out every mine layout.
+    let probabilities: Option<Vec<f64>> =
+        if options.heatmap && *map.get_status() == Status::InProgress {
+            solver::mine_probabilities(map)
+        } else {
+            None
+        };
+
+    // Classify each tile the same way as text rendering does.
+    let text_options = RenderOptions {
+        revealed: options.revealed,
+        ..RenderOptions::plain()
+    };
+    let gap: f32 = (size / 32.0).max(1.0);
+    for index in 0..map.get_tiles().len() {
+        if !map.tile_exists(index) {
+            continue;
+        }
+        let tile = map.get_tile(index);
+        let symbol: char = tile_glyph(map, index, &text_options).symbol;
+        let x: f32 = (index % map.get_width() as usize) as f32 * size;
+        let y: f32 = (index / map.get_width() as usize) as f32 * size;
+
+        let fill: Rgb = match symbol {
+            '#' | '^' | 'v' | 'X' => theme.hidden,
+            '*' | '~' if tile.flipped => theme.exploded,
+            _ => theme.flipped,
+        };
+        shapes.push(Shape::Rect {
+            x: x + gap / 2.0,
+            y: y + gap / 2.0,
+            width: size - gap,
+            height: size - gap,
+            fill,
+            opacity: 1.0,
+        });
+        if let (Some(probabilities), '#') = (&probabilities, symbol) {
+            shapes.push(Shape::Rect {
+                x: x + gap / 2.0,
+                y: y + gap / 2.0,
+                width: size - gap,
+                height: size - gap,
+                fill: theme.heatmap,
+                opacity: probabilities[index] as f32 * 0.85,
+            });
+        }
+
+        let mut painter = TilePainter {
+            shapes: &mut shapes,
+            x,
+            y,
+            size,
+        };
+        match symbol {
+            '^' => painter.flag(theme, false),
+            'v' => painter.flag(theme, true),
+            'X' => {
+                painter.flag(theme, tile.flags < 0);
+                painter.line(&[(3.0, 3.0), (13.0, 13.0)], theme.wrong, 1.5);
+                painter.line(&[(13.0, 3.0), (3.0, 13.0)], theme.wrong, 1.5);
+            }
+            '*' | '~' => painter.mine(theme, fill, symbol == '~'),
+            '#' | '.' => {}
+            _ => painter.number(theme, tile.value),
+        }
+    }
+
+    (width, height, shapes)
+}
+
+/// Format a coordinate without needless decimal places.
+fn number(value: f32) -> String {
+    let rounded: String = format!("{:.2}", value);
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn points(points: &[(f32, f32)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{},{}", number(*x), number(*y)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Draw the map as an SVG image.
+///
+/// ```
+/// use casspir::{map, point};
+/// use casspir::render::image::{self, ImageOptions};
+///
+/// let click = point::Point { x: 4, y: 4 };
+/// let mut map = map::generate_map_with_total(9, 9, 10, click.clone());
+/// map.flip(&click);
+///
+/// let mut svg: Vec<u8> = Vec::new();
+/// image::write_svg(&map, &mut svg, &ImageOptions::default()).unwrap();
+/// assert!(String::from_utf8(svg).unwrap().starts_with("<svg"));
+/// ```
+pub fn write_svg(map: &Map, writer: &mut dyn Write, options: &ImageOptions) -> io::Result<()> {
+    let (width, height, shapes) = build_scene(map, options);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height
+    )?;
+    for shape in shapes {
+        match shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                opacity,
+            } => {
+                write!(
+                    writer,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}""#,
+                    number(x),
+                    number(y),
+                    number(width),
+                    number(height),
+                    fill.hex()
+                )?;
+                if opacity < 1.0 {
+                    write!(writer, r#" fill-opacity="{}""#, number(opacity))?;
+                }
+                writeln!(writer, "/>")?;
+            }
+            Shape::Circle { x, y, radius, fill } => writeln!(
+                writer,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                number(x),
+                number(y),
+                number(radius),
+                fill.hex()
+            )?,
+            Shape::Polygon { points: p, fill } => writeln!(
+                writer,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                points(&p),
+                fill.hex()
+            )?,
+            Shape::Line {
+                points: p,
+                stroke,
+                width,
+            } => writeln!(
+                writer,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                points(&p),
+                stroke.hex(),
+                number(width)
+            )?,
+        }
+    }
+    writeln!(writer, "</svg>")
+}
+
+/// Draw the map as a PNG image.
+///
+/// ```
+/// use casspir::{map, point};
+/// use casspir::render::image::{self, ImageOptions};
+///
+/// let map = map::generate_map_with_total(9, 9, 10, point::Point { x: 4, y: 4 });
+/// let mut png: Vec<u8> = Vec::new();
+/// image::write_png(&map, &mut png, &ImageOptions::default()).unwrap();
+/// assert_eq!(b"\x89PNG", &png[..4]);
+/// ```
+#[cfg(feature = "png")]
+pub fn write_png(map: &Map, writer: &mut dyn Write, options: &ImageOptions) -> io::Result<()> {
+    use tiny_skia::{
+        Color, FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform,
+    };
+
+    let (width, height, shapes) = build_scene(map, options);
+    let mut pixmap = Pixmap::new(width.max(1), height.max(1))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The image is too large."))?;
+    let paint = |color: Rgb, opacity: f32| {
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_rgba8(
+            color.0,
+            color.1,
+            color.2,
+            (opacity.clamp(0.0, 1.0) * 255.0).round() as u8,
+        ));
+        paint.anti_alias = true;
+        paint
+    };
+    let path = |points: &[(f32, f32)], close: bool| {
+        let mut builder = PathBuilder::new();
+        for (i, (x, y)) in points.iter().enumerate() {
+            if i == 0 {
+                builder.move_to(*x, *y);
+            } else {
+                builder.line_to(*x, *y);
+            }
+        }
+        if close {
+            builder.close();
+        }
+        builder.finish()
+    };
+
+    for shape in shapes {
+        match shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+                opacity,
+            } => {
+                if let Some(rect) = tiny_skia::Rect::from_xywh(x, y, width, height) {
+                    pixmap.fill_rect(rect, &paint(fill, opacity), Transform::identity(), None);
+                }
+            }
+            Shape::Circle { x, y, radius, fill } => {
+                if let Some(circle) = PathBuilder::from_circle(x, y, radius) {
+                    let paint = paint(fill, 1.0);
+                    pixmap.fill_path(
+                        &circle,
+                        &paint,
+                        FillRule::Winding,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+            Shape::Polygon { points, fill } => {
+                if let Some(polygon) = path(&points, true) {
+                    let paint = paint(fill, 1.0);
+                    pixmap.fill_path(
+                        &polygon,
+                        &paint,
+                        FillRule::Winding,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+            Shape::Line {
+                points,
+                stroke,
+                width,
+            } => {
+                if let Some(line) = path(&points, false) {
+                    let style = Stroke {
+                        width,
+                        line_cap: LineCap::Round,
+                        line_join: LineJoin::Round,
+                        ..Stroke::default()
+                    };
+                    pixmap.stroke_path(
+                        &line,
+                        &paint(stroke, 1.0),
+                        &style,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    let png: Vec<u8> = pixmap.encode_png().map_err(io::Error::other)?;
+    writer.write_all(&png)
+}
+
+/// Draw the map as an image in the given `format`.
+pub fn write_image(
+    map: &Map,
+    writer: &mut dyn Write,
+    options: &ImageOptions,
+    format: ImageFormat,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Svg => write_svg(map, writer, options),
+        #[cfg(feature = "png")]
+        ImageFormat::Png => write_png(map, writer, options),
+    }
+}
+
+/// Get the state of the map before the `moves` and after each of them.
+pub fn solve_frames(map: &Map, moves: &VecDeque<Move>) -> Vec<Map> {
+    let mut frame: Map = map.clone();
+    let mut frames: Vec<Map> = vec![frame.clone()];
+    for play in moves {
+        frame.apply_moves(&std::iter::once(play.clone()).collect());
+        frames.push(frame.clone());
+    }
+    frames
+}
+
+/// Draw an image of the map before the `moves` and after each of them, such as the moves
+/// from `solver::solve`, for use as the frames of an animation.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use casspir::render::image::{self, ImageFormat, ImageOptions};
+///
+/// let click = point::Point { x: 4, y: 4 };
+/// let mut map = map::generate_map_with_total(9, 9, 10, click.clone());
+/// map.flip(&click);
+/// let moves = solver::solve(&map);
+///
+/// let frames = image::render_frames(&map, &moves, &ImageOptions::default(), ImageFormat::Svg).unwrap();
+/// assert_eq!(moves.len() + 1, frames.len());
+/// ```
+pub fn render_frames(
+    map: &Map,
+    moves: &VecDeque<Move>,
+    options: &ImageOptions,
+    format: ImageFormat,
+) -> io::Result<Vec<Vec<u8>>> {
+    solve_frames(map, moves)
+        .iter()
+        .map(|frame| {
+            let mut image: Vec<u8> = Vec::new();
+            write_image(frame, &mut image, options, format)?;
+            Ok(image)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::point::Point;
+    use std::collections::HashSet;
+
+    fn svg_string(map: &Map, options: &ImageOptions) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        write_svg(map, &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_svg() {
+        // Create a 3x1 map with a mine on the end.
+        let mines: HashSet<Point> = [Point { x: 2, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(3, 1, mines);
+        let options = ImageOptions {
+            tile_size: 16,
+            ..ImageOptions::default()
+        };
+
+        let svg = svg_string(&map, &options);
+        assert!(svg.contains(r#"width="48" height="16""#));
+        assert_eq!(3, svg.matches("#bdbdbd").count());
+        assert!(!svg.contains("<circle"));
+
+        // Revealing the board shows the mine, and the number in its classic colour.
+        let revealed = ImageOptions {
+            revealed: true,
+            ..options.clone()
+        };
+        let svg = svg_string(&map, &revealed);
+        assert!(svg.contains("<circle"));
+        assert!(svg.contains("#0000ff"));
+
+        // The heatmap only covers the hidden tiles, which each have an even chance of a mine.
+        map.reveal(&Point { x: 1, y: 0 });
+        let heatmap = ImageOptions {
+            heatmap: true,
+            ..options.clone()
+        };
+        let svg = svg_string(&map, &heatmap);
+        assert_eq!(2, svg.matches("fill-opacity").count());
+
+        // The exploded mine is drawn on its own colour.
+        map.flip(&Point { x: 2, y: 0 });
+        let svg = svg_string(&map, &options);
+        assert!(svg.contains(r##"fill="#ff0000"/>"##));
+    }
+
+    #[test]
+    fn test_heatmap_with_wrong_flag() {
+        // Create a 5x1 map with a mine on the end, revealing the clue next to it.
+        let mines: HashSet<Point> = [Point { x: 4, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&Point { x: 3, y: 0 });
+
+        // Flagging the other end uses up the only mine, so no layout fits and nothing is shaded.
+        map.flag(&Point { x: 0, y: 0 });
+        let options = ImageOptions {
+            heatmap: true,
+            ..ImageOptions::default()
+        };
+        let svg = svg_string(&map, &options);
+        assert_eq!(0, svg.matches("fill-opacity").count());
+    }
+
+    #[test]
+    fn test_frames() {
+        let mines: HashSet<Point> = [Point { x: 2, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(3, 1, mines);
+        map.flip(&Point { x: 0, y: 0 });
+        let moves: VecDeque<Move> = solver::solve(&map);
+        let frames = solve_frames(&map, &moves);
+        assert_eq!(moves.len() + 1, frames.len());
+        assert!(frames[0] == map);
+        assert_eq!(Status::Complete, *frames.last().unwrap().get_status());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png() {
+        let map = map::generate_map_with_mines(3, 2, HashSet::new());
+        let mut png: Vec<u8> = Vec::new();
+        write_png(&map, &mut png, &ImageOptions::default()).unwrap();
+        assert_eq!(b"\x89PNG", &png[..4]);
+
+        // The width and height are stored big endian in the header.
+        assert_eq!(96, u32::from_be_bytes([png[16], png[17], png[18], png[19]]));
+        assert_eq!(64, u32::from_be_bytes([png[20], png[21], png[22], png[23]]));
+    }
+}
@@ -0,0 +1,351 @@
+//! This module contains tools for drawing a map for people to look at.
+
+use crate::map::{Map, Status};
+use std::io::{self, Write};
+
+pub mod heatmap;
+pub mod image;
+
+/// The colours used to draw tiles, named after the usual terminal colours.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Color {
+    Blue,
+    Green,
+    Red,
+    DarkBlue,
+    DarkRed,
+    Cyan,
+    Magenta,
+    Grey,
+    DarkGrey,
+    Yellow,
+    White,
+}
+
+impl Color {
+    /// The ANSI code that sets this colour as the foreground, add 10 for the background.
+    pub fn ansi_code(&self) -> u8 {
+        match self {
+            Color::Blue => 94,
+            Color::Green => 32,
+            Color::Red => 91,
+            Color::DarkBlue => 34,
+            Color::DarkRed => 31,
+            Color::Cyan => 36,
+            Color::Magenta => 35,
+            Color::Grey => 37,
+            Color::DarkGrey => 90,
+            Color::Yellow => 93,
+            Color::White => 97,
+        }
+    }
+}
+
+/// The classic colour of each tile value.
+pub fn value_color(value: i16) -> Color {
+    match value.abs() {
+        1 => Color::Blue,
+        2 => Color::Green,
+        3 => Color::Red,
+        4 => Color::DarkBlue,
+        5 => Color::DarkRed,
+        6 => Color::Cyan,
+        7 => Color::Magenta,
+        _ => Color::Grey,
+    }
+}
+
+/// How a single tile is drawn.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Glyph {
+    pub symbol: char,
+    pub color: Option<Color>,
+    pub background: Option<Color>,
+}
+
+impl Glyph {
+    fn new(symbol: char, color: Option<Color>) -> Glyph {
+        Glyph {
+            symbol,
+            color,
+            background: None,
+        }
+    }
+}
+
+/// Options for drawing a map as text.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Colour the tiles using ANSI escape codes.
+    pub color: bool,
+    /// Draw flags, mines and hidden tiles with Unicode symbols rather than ASCII.
+    pub unicode: bool,
+    /// Label the columns and rows with their coordinates.
+    pub axes: bool,
+    /// Show what is under every tile, as `Map::print` does when revealed.
+    pub revealed: bool,
+}
+
+impl RenderOptions {
+    /// ASCII symbols without colours or labels.
+    pub fn plain() -> RenderOptions {
+        RenderOptions {
+            color: false,
+            unicode: false,
+            axes: false,
+            revealed: false,
+        }
+    }
+
+    /// Colours, Unicode symbols and labels, for a terminal that supports them.
+    pub fn terminal() -> RenderOptions {
+        RenderOptions {
+            color: true,
+            unicode: true,
+            axes: true,
+            revealed: false,
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions::plain()
+    }
+}
+
+/// Get how the tile at `index` is drawn. Once a game is lost every mine is shown,
+/// along with the mine that exploded and any flags that were wrong.
+/// Once a game is won the remaining mines are shown flagged.
+///
+/// ```
+/// use casspir::{map, point, render};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(3, 1, mines);
+/// map.flag(&point::Point { x: 1, y: 0 });
+/// map.flip(&point::Point { x: 0, y: 0 });
+///
+/// let options = render::RenderOptions::plain();
+/// let exploded = render::tile_glyph(&map, 0, &options);
+/// assert_eq!('*', exploded.symbol);
+/// assert_eq!(Some(render::Color::Red), exploded.background);
+/// assert_eq!('X', render::tile_glyph(&map, 1, &options).symbol);
+/// ```
+pub fn tile_glyph(map: &Map, index: usize, options: &RenderOptions) -> Glyph {
+    let tile = map.get_tile(index);
+    let status: &Status = map.get_status();
+    let symbols = |ascii: char, unicode: char| if options.unicode { unicode } else { ascii };
+    let mine = |mines: i8| {
+        if mines < 0 {
+            symbols('~', '○')
+        } else {
+            symbols('*', '●')
+        }
+    };
+    let flag = |flags: i8| {
+        if flags < 0 {
+            symbols('v', '⚐')
+        } else {
+            symbols('^', '⚑')
+        }
+    };
+
+    if !map.tile_exists(index) {
+        Glyph::new(' ', None)
+    } else if tile.flipped && tile.is_mine() {
+        Glyph {
+            symbol: mine(tile.mines),
+            color: Some(Color::White),
+            background: Some(Color::Red),
+        }
+    } else if tile.flipped || (options.revealed && !tile.is_mine()) {
+        match tile.value {
+            0 => Glyph::new(symbols('.', '·'), Some(Color::DarkGrey)),
+            value => Glyph::new(
+                char::from_digit(value.unsigned_abs() as u32, 10).unwrap_or('+'),
+                Some(value_color(value)),
+            ),
+        }
+    } else if tile.is_flagged() && *status == Status::Failed && tile.flags != tile.mines {
+        Glyph::new(symbols('X', '✗'), Some(Color::Red))
+    } else if tile.is_flagged() {
+        Glyph::new(flag(tile.flags), Some(Color::Red))
+    } else if tile.is_mine() && *status == Status::Complete {
+        Glyph::new(flag(tile.mines), Some(Color::Red))
+    } else if tile.is_mine() && (options.revealed || *status == Status::Failed) {
+        Glyph::new(mine(tile.mines), Some(Color::White))
+    } else {
+        Glyph::new(symbols('#', '■'), Some(Color::DarkGrey))
+    }
+}
+
+/// Write the `symbol` in the given colours, if colours are enabled.
+pub(crate) fn write_colored(
+    writer: &mut dyn Write,
+    symbol: impl std::fmt::Display,
+    color: Option<Color>,
+    background: Option<Color>,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    if !options.color || (color.is_none() && background.is_none()) {
+        return write!(writer, "{}", symbol);
+    }
+    if let Some(color) = color {
+        write!(writer, "\x1b[{}m", color.ansi_code())?;
+    }
+    if let Some(background) = background {
+        write!(writer, "\x1b[{}m", background.ansi_code() + 10)?;
+    }
+    write!(writer, "{}\x1b[0m", symbol)
+}
+
+/// The number of characters needed to label every row of a map `height` tall.
+pub(crate) fn row_label_width(height: u16) -> usize {
+    (height.max(1) - 1).to_string().len()
+}
+
+/// Write the column numbers above a map, one line per digit, with each column `spacing` wide.
+pub(crate) fn write_column_labels(
+    writer: &mut dyn Write,
+    width: u16,
+    height: u16,
+    spacing: usize,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let digits: u32 = (width.max(1) - 1).to_string().len() as u32;
+    for digit in (0..digits).rev() {
+        write!(writer, "{:1$}", "", row_label_width(height))?;
+        for x in 0..width {
+            let place: u16 = 10u16.pow(digit);
+            // Only label the higher digits where they change, so wide maps stay readable.
+            let label: String = if digit == 0 || (x >= place && x % place == 0) {
+                ((x / place) % 10).to_string()
+            } else {
+                " ".into()
+            };
+            write!(writer, "{:>1$}", "", spacing - 1)?;
+            write_colored(writer, label, Some(Color::DarkGrey), None, options)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write the label at the start of row `y`.
+pub(crate) fn write_row_label(
+    writer: &mut dyn Write,
+    y: u16,
+    height: u16,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    write_colored(
+        writer,
+        format!("{:>1$}", y, row_label_width(height)),
+        Some(Color::DarkGrey),
+        None,
+        options,
+    )
+}
+
+/// Draw the map to the `writer` as rows of text, one tile per character with a space
+/// before each. Unlike `Map::print` there is no leading newline.
+///
+/// ```
+/// use casspir::{map, point, render};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(3, 2, mines);
+/// map.flip(&point::Point { x: 2, y: 1 });
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// let options = render::RenderOptions {
+///     axes: true,
+///     ..render::RenderOptions::plain()
+/// };
+/// render::render(&map, &mut out, &options).unwrap();
+/// assert_eq!("  0 1 2\n0 # 1 .\n1 # 1 .\n", String::from_utf8(out).unwrap());
+/// ```
+pub fn render(map: &Map, writer: &mut dyn Write, options: &RenderOptions) -> io::Result<()> {
+    let (width, height) = (map.get_width(), map.get_height());
+    if options.axes {
+        write_column_labels(writer, width, height, 2, options)?;
+    }
+    for y in 0..height {
+        if options.axes {
+            write_row_label(writer, y, height, options)?;
+        }
+        for x in 0..width {
+            let glyph = tile_glyph(map, y as usize * width as usize + x as usize, options);
+            write!(writer, " ")?;
+            write_colored(writer, glyph.symbol, glyph.color, glyph.background, options)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::point::Point;
+    use std::collections::HashSet;
+
+    fn render_string(map: &Map, options: &RenderOptions) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        render(map, &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        // Create a 4x1 map with a mine on each end.
+        let mines: HashSet<Point> = [Point { x: 0, y: 0 }, Point { x: 3, y: 0 }]
+            .iter()
+            .cloned()
+            .collect();
+        let mut map = map::generate_map_with_mines(4, 1, mines);
+        map.flip(&Point { x: 1, y: 0 });
+        map.flag(&Point { x: 2, y: 0 });
+
+        assert_eq!(" # 1 ^ #\n", render_string(&map, &RenderOptions::plain()));
+        let revealed = RenderOptions {
+            revealed: true,
+            ..RenderOptions::plain()
+        };
+        assert_eq!(" * 1 1 *\n", render_string(&map, &revealed));
+
+        // Colours wrap each tile, and the value takes the classic blue.
+        let colored = render_string(&map, &RenderOptions::terminal());
+        assert!(colored.contains("\x1b[94m1\x1b[0m"));
+        assert!(colored.contains("⚑"));
+
+        // After a loss the exploded mine, the other mines and the wrong flag are all shown.
+        map.flip(&Point { x: 0, y: 0 });
+        assert_eq!(" * 1 X *\n", render_string(&map, &RenderOptions::plain()));
+        let lost = render_string(&map, &RenderOptions::terminal());
+        assert!(lost.contains("\x1b[97m\x1b[101m●\x1b[0m"));
+        assert!(lost.contains("✗"));
+    }
+
+    #[test]
+    fn test_axes() {
+        let map = map::generate_map_with_mines(12, 11, HashSet::new());
+        let options = RenderOptions {
+            axes: true,
+            ..RenderOptions::plain()
+        };
+        let rendered = render_string(&map, &options);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        // Two digit columns get a line of tens, and rows are padded to line up.
+        assert_eq!(13, lines.len());
+        assert_eq!(format!("{:22} 1", ""), lines[0].trim_end());
+        assert_eq!("   0 1 2 3 4 5 6 7 8 9 0 1", lines[1]);
+        assert_eq!(" 0 # # # # # # # # # # # #", lines[2]);
+        assert_eq!("10 # # # # # # # # # # # #", lines[12]);
+    }
+}
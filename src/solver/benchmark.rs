@@ -0,0 +1,289 @@
+//! Measures how well the solver plays over many generated boards.
+
+use super::{count_guesses, solve_with_rng, GuessStrategy};
+use crate::batch;
+use crate::map::{self, Status};
+use crate::point::Point;
+use std::fmt;
+use std::time::Instant;
+
+/// The boards to generate for a benchmark and how to solve them.
+#[derive(PartialEq, Clone, Debug)]
+pub struct BenchmarkConfig {
+    /// The width of each board.
+    pub width: u16,
+    /// The height of each board.
+    pub height: u16,
+    /// The number of mines on each board.
+    pub mines: u32,
+    /// The number of boards to solve.
+    pub games: u32,
+    /// How the solver picks guesses.
+    pub strategy: GuessStrategy,
+    /// The seed the boards and guesses are drawn from, so strategies can be compared on the same boards.
+    pub seed: u64,
+}
+
+impl BenchmarkConfig {
+    /// A 9x9 board with 10 mines.
+    pub fn beginner(games: u32) -> BenchmarkConfig {
+        BenchmarkConfig::new(9, 9, 10, games)
+    }
+
+    /// A 16x16 board with 40 mines.
+    pub fn intermediate(games: u32) -> BenchmarkConfig {
+        BenchmarkConfig::new(16, 16, 40, games)
+    }
+
+    /// A 30x16 board with 99 mines.
+    pub fn expert(games: u32) -> BenchmarkConfig {
+        BenchmarkConfig::new(30, 16, 99, games)
+    }
+
+    /// Solve `games` boards of the given size, with the solver's default guesses and a seed of 0.
+    pub fn new(width: u16, height: u16, mines: u32, games: u32) -> BenchmarkConfig {
+        BenchmarkConfig {
+            width,
+            height,
+            mines,
+            games,
+            strategy: GuessStrategy::LowestRisk,
+            seed: 0,
+        }
+    }
+}
+
+/// The mean of a measurement along with the margin of its 95% confidence interval.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Statistic {
+    pub mean: f64,
+    /// Half the width of the confidence interval, so the interval is `mean ± margin`.
+    pub margin: f64,
+}
+
+impl Statistic {
+    /// Summarise the given `samples` using a normal approximation.
+    pub fn from_samples(samples: &[f64]) -> Statistic {
+        let count: f64 = samples.len() as f64;
+        if samples.is_empty() {
+            return Statistic {
+                mean: 0.0,
+                margin: 0.0,
+            };
+        }
+
+        let mean: f64 = samples.iter().sum::<f64>() / count;
+        if samples.len() == 1 {
+            return Statistic { mean, margin: 0.0 };
+        }
+        let variance: f64 = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / (count - 1.0);
+
+        Statistic {
+            mean,
+            margin: 1.96 * (variance / count).sqrt(),
+        }
+    }
+}
+
+impl fmt::Display for Statistic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.3} ± {:.3}", self.mean, self.margin)
+    }
+}
+
+/// The results of a benchmark.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Report {
+    /// The configuration that was run.
+    pub config: BenchmarkConfig,
+    /// The number of games won.
+    pub wins: u32,
+    /// The share of games won.
+    pub win_rate: Statistic,
+    /// The number of moves per game that might have hit a mine, not counting the first click.
+    pub guesses: Statistic,
+    /// The number of moves per game.
+    pub moves: Statistic,
+    /// The time taken to solve each game, in milliseconds.
+    pub time: Statistic,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}x{} with {} mines, {} games, {:?}, seed {}",
+            self.config.width,
+            self.config.height,
+            self.config.mines,
+            self.config.games,
+            self.config.strategy,
+            self.config.seed
+        )?;
+        writeln!(f, "  wins:     {}", self.wins)?;
+        writeln!(f, "  win rate: {}", self.win_rate)?;
+        writeln!(f, "  guesses:  {}", self.guesses)?;
+        writeln!(f, "  moves:    {}", self.moves)?;
+        write!(f, "  time (ms): {}", self.time)
+    }
+}
+
+/// Generate and solve boards as described by the `config`, reporting how well the solver did.
+/// Each game starts with a safe click in the centre of the board.
+/// The boards are those `batch::generate_batch` makes from the same seed, whatever the strategy.
+///
+/// ```
+/// use casspir::solver;
+/// let report = solver::benchmark(solver::BenchmarkConfig::beginner(10));
+/// assert!(report.wins <= 10);
+/// assert!(report.win_rate.mean >= 0.0 && report.win_rate.mean <= 1.0);
+/// ```
+pub fn benchmark(config: BenchmarkConfig) -> Report {
+    let click = Point {
+        x: config.width / 2,
+        y: config.height / 2,
+    };
+
+    let mut wins: Vec<f64> = Vec::new();
+    let mut guesses: Vec<f64> = Vec::new();
+    let mut moves: Vec<f64> = Vec::new();
+    let mut time: Vec<f64> = Vec::new();
+    for game in 0..config.games as usize {
+        let mut rng = batch::item_rng(config.seed, game);
+        let mut map = map::generate_map_with_total_and_rng(
+            config.width,
+            config.height,
+            config.mines,
+            click.clone(),
+            &mut rng,
+        );
+        map.flip(&click);
+
+        let start = Instant::now();
+        let solution = solve_with_rng(&map, config.strategy, &mut rng);
+        time.push(start.elapsed().as_secs_f64() * 1000.0);
+
+        // Guesses are found by replaying the moves, which isn't part of the time spent solving.
+        let guessed: u32 = count_guesses(&map, &solution);
+        map.apply_moves(&solution);
+        wins.push(if *map.get_status() == Status::Complete {
+            1.0
+        } else {
+            0.0
+        });
+        guesses.push(guessed as f64);
+        moves.push(solution.len() as f64);
+    }
+
+    Report {
+        wins: wins.iter().sum::<f64>() as u32,
+        win_rate: Statistic::from_samples(&wins),
+        guesses: Statistic::from_samples(&guesses),
+        moves: Statistic::from_samples(&moves),
+        time: Statistic::from_samples(&time),
+        config,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{find_guesses, solve_noting_guesses};
+    use super::*;
+
+    #[test]
+    fn test_statistic() {
+        let statistic = Statistic::from_samples(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(2.5, statistic.mean);
+        assert!((statistic.margin - 1.96 * (5.0f64 / 12.0).sqrt()).abs() < 1e-9);
+
+        // Too few samples have no spread.
+        assert_eq!(0.0, Statistic::from_samples(&[]).margin);
+        assert_eq!(0.0, Statistic::from_samples(&[1.0]).margin);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let mut config = BenchmarkConfig::new(6, 6, 4, 20);
+        config.strategy = GuessStrategy::WinProbability;
+        let report = benchmark(config.clone());
+
+        assert_eq!(config, report.config);
+        assert_eq!(report.wins as f64 / 20.0, report.win_rate.mean);
+        // Every guess is a move.
+        assert!(report.moves.mean >= report.guesses.mean);
+        assert!(report.time.margin >= 0.0);
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        ignore = "timings are only meaningful in a release build"
+    )]
+    fn test_expert_time() {
+        // An expert game solves in about a third of a millisecond, and took three times as long
+        // when every guess worked out the chances across the whole board.
+        let report = benchmark(BenchmarkConfig::expert(300));
+        assert!(report.time.mean < 0.75, "{}", report);
+    }
+
+    #[test]
+    fn test_guesses_are_risky_moves() {
+        // Replay the games the benchmark plays, counting the moves that might have hit a mine.
+        // Each of them is one the solver noted as a possible guess, so its certain moves are sound.
+        for strategy in [GuessStrategy::LowestRisk, GuessStrategy::WinProbability] {
+            let config = BenchmarkConfig {
+                strategy,
+                ..BenchmarkConfig::expert(20)
+            };
+            let click = Point { x: 15, y: 8 };
+            let mut risky: Vec<f64> = Vec::new();
+            for game in 0..config.games as usize {
+                let mut rng = batch::item_rng(config.seed, game);
+                let mut map =
+                    map::generate_map_with_total_and_rng(30, 16, 99, click.clone(), &mut rng);
+                map.flip(&click);
+                let (solution, noted) = solve_noting_guesses(&map, strategy, &mut rng);
+                let guesses: Vec<usize> = find_guesses(&map, &solution);
+                assert!(guesses.iter().all(|guess| noted.contains(guess)));
+                risky.push(guesses.len() as f64);
+            }
+            assert_eq!(Statistic::from_samples(&risky), benchmark(config).guesses);
+        }
+    }
+
+    #[test]
+    fn test_win_probability_wins_more() {
+        // Both strategies play the same boards, and picking guesses across the whole board does no worse.
+        for config in [
+            BenchmarkConfig::beginner(100),
+            BenchmarkConfig::intermediate(50),
+        ] {
+            let lowest_risk = benchmark(config.clone());
+            let win_probability = benchmark(BenchmarkConfig {
+                strategy: GuessStrategy::WinProbability,
+                ..config
+            });
+            assert!(win_probability.wins >= lowest_risk.wins);
+        }
+    }
+
+    #[test]
+    fn test_seeded_benchmark() {
+        // The same seed plays the same games, and another seed plays different ones.
+        let config = BenchmarkConfig {
+            seed: 3,
+            ..BenchmarkConfig::beginner(20)
+        };
+        let first = benchmark(config.clone());
+        let second = benchmark(config.clone());
+        assert_eq!(first.wins, second.wins);
+        assert_eq!(first.guesses, second.guesses);
+        assert_eq!(first.moves, second.moves);
+        let other = benchmark(BenchmarkConfig { seed: 4, ..config });
+        assert_ne!(first.moves, other.moves);
+    }
+}
@@ -1,20 +1,48 @@
 //! This module contains tools for solving a puzzle.
 
-use crate::map::{Map, Status, Tile};
+use crate::map::{Bitboard, Map, Mode, Status, Tile};
 use crate::point::{self, Point};
-use rand;
-use std::cmp::min;
+use rand::seq::SliceRandom;
+use rand::{self, Rng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::cmp::{max, min, Reverse};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::iter::FromIterator;
 
+mod benchmark;
+mod rating;
+
+pub use self::benchmark::{benchmark, BenchmarkConfig, Report, Statistic};
+pub use self::rating::{find_opening, rate, three_bv, Rating};
+
 const GROUP_SIZE_LIMIT: usize = 18;
 
+/// The most tiles that can each hold one of `radix` mine counts without having more
+/// permutations than `single_limit` tiles that hold at most one mine.
+fn tile_limit(radix: u32, single_limit: usize) -> usize {
+    let mut limit: usize = 0;
+    let mut permutations: u32 = radix;
+    while permutations <= 1 << single_limit {
+        permutations *= radix;
+        limit += 1;
+    }
+    limit
+}
+
+/// The largest group that can be enumerated when each tile can hold one of `radix` mine counts,
+/// such that there are no more permutations than a group of `GROUP_SIZE_LIMIT` single mine tiles.
+fn group_size_limit(radix: u32) -> usize {
+    tile_limit(radix, GROUP_SIZE_LIMIT)
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum MoveType {
     Flip,
     Flag,
+    NegativeFlag,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -23,28 +51,247 @@ pub struct Move {
     pub move_type: MoveType,
 }
 
+/// How the solver picks a tile to flip when no tile is certain to be safe.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GuessStrategy {
+    /// Flip the least risky tile of the groups along the border, or a random tile if there are none.
+    LowestRisk,
+    /// Flip the tile most likely to be safe across the whole board, favouring tiles
+    /// likely to open up the board around them.
+    /// Once few enough tiles are hidden, the move is found by `solve_endgame` instead.
+    /// Over 1000 games of `benchmark` with a seed of 0, wins 87.3% of beginner, 70.6% of
+    /// intermediate and 35.3% of expert games, against 65.1%, 44.6% and 6.3% for `LowestRisk`.
+    WinProbability,
+}
+
+/// The most hidden tiles a position can have for `solve_endgame` to search it,
+/// when each tile holds at most one mine.
+const ENDGAME_TILE_LIMIT: usize = 12;
+
+/// The most hidden tiles `solve_endgame` searches when each tile can hold one of `radix` mine
+/// counts, such that there are no more layouts than `ENDGAME_TILE_LIMIT` single mine tiles have.
+fn endgame_tile_limit(radix: u32) -> usize {
+    tile_limit(radix, ENDGAME_TILE_LIMIT)
+}
+
+/// Chances of a mine closer to 0 than this are treated as certainly safe, to allow for rounding.
+pub(crate) const CERTAINTY_TOLERANCE: f64 = 1e-9;
+
+/// How much a guess is favoured for the chance that it opens up the board around it,
+/// relative to the chance that it is safe.
+const OPENING_WEIGHT: f64 = 0.2;
+
 /// Solve the given map and produce a queue of moves representing the solution.
+/// Random guesses are drawn from `thread_rng`, use `solve_with_rng` for repeatable moves.
+/// On kind and cruel maps the moves end at the first guess that would hit a mine where the mines
+/// are now, since the map may move them, so solve again after applying the moves to carry on.
 pub fn solve(map: &Map) -> VecDeque<Move> {
+    solve_with_strategy(map, GuessStrategy::LowestRisk)
+}
+
+/// Solve the given map like `solve`, picking guesses with the given `strategy`.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// let mut map = map::generate_map_with_total(16, 16, 40, point::Point { x: 8, y: 8 });
+/// map.flip(&point::Point { x: 8, y: 8 });
+/// let moves = solver::solve_with_strategy(&map, solver::GuessStrategy::WinProbability);
+/// map.apply_moves(&moves);
+/// assert_ne!(map::Status::InProgress, *map.get_status());
+/// ```
+pub fn solve_with_strategy(map: &Map, strategy: GuessStrategy) -> VecDeque<Move> {
+    solve_noting_guesses(map, strategy, &mut rand::thread_rng()).0
+}
+
+/// Solve the given map like `solve_with_strategy`, making any random guesses with the given `rng`.
+/// Moves are made in an order that depends only on the map, so the same map and seeded `rng`
+/// always produce the same moves, with or without the `parallel` feature.
+/// This holds on kind and cruel maps too, as moves are planned against the mines where they are now.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let click = point::Point { x: 8, y: 8 };
+/// let mut map = map::generate_map_with_total(16, 16, 40, click.clone());
+/// map.flip(&click);
+/// let strategy = solver::GuessStrategy::LowestRisk;
+/// let moves = solver::solve_with_rng(&map, strategy, &mut StdRng::seed_from_u64(7));
+/// assert_eq!(moves, solver::solve_with_rng(&map, strategy, &mut StdRng::seed_from_u64(7)));
+/// ```
+pub fn solve_with_rng<R: Rng + ?Sized>(
+    map: &Map,
+    strategy: GuessStrategy,
+    rng: &mut R,
+) -> VecDeque<Move> {
+    solve_noting_guesses(map, strategy, rng).0
+}
+
+/// Solve the given map like `solve_with_rng`, also producing the positions in the moves of the
+/// flips that might be guesses. Only flips that no pass found certain are listed, and `find_guesses`
+/// works out which of them might really have hit a mine, away from the time spent solving.
+pub(crate) fn solve_noting_guesses<R: Rng + ?Sized>(
+    map: &Map,
+    strategy: GuessStrategy,
+    rng: &mut R,
+) -> (VecDeque<Move>, Vec<usize>) {
+    // Plan against the mines where they are now. Kind and cruel maps move their mines with
+    // their own randomness, so the plan stops at the first guess that hits a mine here.
     let mut staging_map: Map = map.clone();
+    staging_map.set_mode(Mode::Normal);
+    let mut bitboard = Bitboard::from_map(&staging_map);
     let mut moves = VecDeque::<Move>::new();
+    let mut guesses: Vec<usize> = Vec::new();
+    let guess: bool = strategy == GuessStrategy::LowestRisk;
     while *staging_map.get_status() == Status::InProgress {
-        let mut new_moves = basic_pass(&mut staging_map);
-        if new_moves.len() == 0 {
-            new_moves = enumerate_groups(&mut staging_map);
-            if new_moves.len() == 0 {
-                new_moves.push_back(random_move(&mut staging_map));
+        let before: (u32, u32) = (
+            staging_map.get_tiles_flipped(),
+            staging_map.get_mines_remaining(),
+        );
+        let mut new_moves = basic_pass(&mut staging_map, &mut bitboard);
+        if new_moves.is_empty() {
+            let (group_moves, guessed) = enumerate_groups(&mut staging_map, &mut bitboard, guess);
+            new_moves = group_moves;
+            if guessed {
+                guesses.push(moves.len());
+            }
+            if new_moves.is_empty() {
+                // Tiles found to be safe by looking at the whole board aren't guesses.
+                let (play, safe) = match strategy {
+                    GuessStrategy::LowestRisk => (random_move(&mut staging_map, rng), false),
+                    GuessStrategy::WinProbability => match endgame_move(&staging_map) {
+                        Some((position, _, safe)) => {
+                            staging_map.flip(&position);
+                            let play = Move {
+                                position,
+                                move_type: MoveType::Flip,
+                            };
+                            (play, safe)
+                        }
+                        None => {
+                            let (play, probability) = win_probability_move(&mut staging_map)
+                                .unwrap_or_else(|| (random_move(&mut staging_map, rng), 1.0));
+                            (play, probability < CERTAINTY_TOLERANCE)
+                        }
+                    },
+                };
+                if !safe {
+                    guesses.push(moves.len());
+                }
+                new_moves.push_back(play);
             }
         }
+
+        // Moves the map refuses, such as flags once the flags already placed account for every mine,
+        // would only be planned again.
+        let after: (u32, u32) = (
+            staging_map.get_tiles_flipped(),
+            staging_map.get_mines_remaining(),
+        );
+        if after == before {
+            break;
+        }
         moves.append(&mut new_moves);
     }
 
-    moves
+    (moves, guesses)
+}
+
+/// Replay the `moves` from the `map`, counting those that weren't certain from what could be seen.
+pub(crate) fn count_guesses(map: &Map, moves: &VecDeque<Move>) -> u32 {
+    find_guesses(map, moves).len() as u32
 }
 
-fn basic_pass(map: &mut Map) -> VecDeque<Move> {
+/// Replay the `moves` from the `map`, producing the positions of those that weren't certain
+/// from what could be seen: flips of tiles that might hold a mine and flags on tiles that might not.
+/// Every move counts while no mine layout is consistent with the map.
+/// Flipping a satisfied flipped tile flips its hidden neighbours, counting if any might hold a mine.
+pub(crate) fn find_guesses(map: &Map, moves: &VecDeque<Move>) -> Vec<usize> {
+    let mut replay: Map = map.clone();
+    replay.set_mode(Mode::Normal);
+    let mut guesses: Vec<usize> = Vec::new();
+    let mut probabilities: Option<Vec<f64>> = None;
+    for (i, play) in moves.iter().enumerate() {
+        if *replay.get_status() != Status::InProgress {
+            break;
+        }
+
+        // A move that is certain stays certain as more tiles are revealed,
+        // so the chances are only worked out again when a move doesn't look certain.
+        let certain: bool = match &probabilities {
+            Some(known) if is_certain_move(&replay, known, play) => true,
+            _ => {
+                probabilities = mine_probabilities(&replay);
+                probabilities
+                    .as_ref()
+                    .is_some_and(|current| is_certain_move(&replay, current, play))
+            }
+        };
+        if !certain {
+            guesses.push(i);
+        }
+
+        replay.apply_moves(&VecDeque::from(vec![play.clone()]));
+    }
+
+    guesses
+}
+
+/// Checks if the move `play` is certain to be right, given the chance each tile holds a mine.
+fn is_certain_move(map: &Map, probabilities: &[f64], play: &Move) -> bool {
+    let index: usize = play.position.to_index(map.get_width());
+    match play.move_type {
+        MoveType::Flip if map.get_tile(index).flipped => {
+            !map.is_tile_satisfied(&play.position)
+                || map.get_adjacent(index).iter().all(|neighbour| {
+                    let tile: &Tile = map.get_tile(*neighbour);
+                    tile.flipped
+                        || tile.is_flagged()
+                        || probabilities[*neighbour] < CERTAINTY_TOLERANCE
+                })
+        }
+        MoveType::Flip => probabilities[index] < CERTAINTY_TOLERANCE,
+        MoveType::Flag | MoveType::NegativeFlag => probabilities[index] > 1.0 - CERTAINTY_TOLERANCE,
+    }
+}
+
+/// Get the smallest mine count a tile can hold and the number of possible counts.
+fn mine_count_range(map: &Map) -> (i32, u32) {
+    let capacity: i32 = map.get_tile_capacity() as i32;
+    if map.has_negative_mines() {
+        (-capacity, 2 * capacity as u32 + 1)
+    } else {
+        (0, capacity as u32 + 1)
+    }
+}
+
+/// Place flags on the tile at `position` to mark the given signed number of `mines`.
+fn flag_mines(map: &mut Map, position: &Point, mines: i8, moves: &mut VecDeque<Move>) {
+    for _ in 0..mines.unsigned_abs() {
+        let move_type = if mines > 0 {
+            map.flag(position);
+            MoveType::Flag
+        } else {
+            map.flag_negative(position);
+            MoveType::NegativeFlag
+        };
+        moves.push_back(Move {
+            position: position.clone(),
+            move_type,
+        });
+    }
+}
+
+/// Evaluate the neighbours of every flipped tile that still has hidden neighbours.
+/// Tiles only ever lose hidden neighbours, so the `bitboard` is synced once up front.
+fn basic_pass(map: &mut Map, bitboard: &mut Bitboard) -> VecDeque<Move> {
+    bitboard.sync(map);
     let mut moves = VecDeque::<Move>::new();
     for i in 0..map.get_tiles().len() {
-        if map.get_tile(i).flipped && map.get_tile(i).value > 0 {
+        let settled: bool =
+            bitboard.get_flipped().contains(i) && bitboard.count_hidden_neighbours(i) == 0;
+        if map.get_tile(i).flipped && map.get_tile(i).value != 0 && !settled {
             moves.append(&mut evaluate_neighbours(map, i));
         }
     }
@@ -56,45 +303,39 @@ fn basic_pass(map: &mut Map) -> VecDeque<Move> {
 /// This is called straight after the given tile is flipped, as the new information
 /// gained by this tiles value could help solve neighbour tiles.
 fn evaluate_neighbours(map: &mut Map, index: usize) -> VecDeque<Move> {
-    let neighbours: HashSet<Point> = point::get_neighbours(
-        &point::from_index(index, map.get_width()),
-        map.get_width(),
-        map.get_height(),
-    );
+    let neighbours: Vec<usize> = map.get_adjacent(index).to_vec();
 
-    let mut flagged: u8 = 0;
-    let mut unflipped: u8 = 0;
+    let mut flags: i32 = 0;
+    let mut hidden: i32 = 0;
     for neighbour in &neighbours {
-        let neighbour_tile: &Tile = &map.get_tile(neighbour.to_index(map.get_width()));
-        if neighbour_tile.flagged {
-            flagged += 1;
-        }
-        if !neighbour_tile.flipped {
-            unflipped += 1;
+        let neighbour_tile: &Tile = map.get_tile(*neighbour);
+        flags += neighbour_tile.flags as i32;
+        if !neighbour_tile.is_flagged() && !neighbour_tile.flipped {
+            hidden += 1;
         }
     }
 
+    let needed: i32 = map.get_tile(index).value as i32 - flags;
+    let capacity: i32 = map.get_tile_capacity() as i32;
     let mut moves = VecDeque::<Move>::new();
 
     // If this tile is satisfied, flip all neighbouring unflipped tiles (via convenience flip on the one tile).
-    if flagged == map.get_tile(index).value && unflipped - flagged > 0 {
+    // Negative mines could cancel each other out, so this only holds without them.
+    if needed == 0 && hidden > 0 && !map.has_negative_mines() {
         let position: Point = point::from_index(index, map.get_width());
         map.flip(&position);
         moves.push_back(Move {
             position,
             move_type: MoveType::Flip,
         });
-    // If the number of unflipped tiles equals this tiles value, they must all be mines.
-    } else if unflipped == map.get_tile(index).value {
-        for neighbour in &neighbours {
-            let neighbour_index = neighbour.to_index(map.get_width());
-            if !map.get_tile(neighbour_index).flagged && !map.get_tile(neighbour_index).flipped {
+    // If the remaining mines would fill every unflagged unflipped tile, they must all be full of mines.
+    } else if needed != 0 && needed.abs() == hidden * capacity {
+        let mines: i8 = (needed.signum() * capacity) as i8;
+        for neighbour_index in neighbours {
+            if !map.get_tile(neighbour_index).is_flagged() && !map.get_tile(neighbour_index).flipped
+            {
                 let position = point::from_index(neighbour_index, map.get_width());
-                map.flag(&position);
-                moves.push_back(Move {
-                    position,
-                    move_type: MoveType::Flag,
-                });
+                flag_mines(map, &position, mines, &mut moves);
             }
         }
     }
@@ -102,55 +343,20 @@ fn evaluate_neighbours(map: &mut Map, index: usize) -> VecDeque<Move> {
     moves
 }
 
-/// Find and solve each discovered group one at a time.
-/// Note: this can be improved by considering distinct groups seperately
-/// that way multiple uncertain moves can be made with one pass because
-/// we know tiles from separate groups won't affect each others solution.
-fn enumerate_groups(map: &mut Map) -> VecDeque<Move> {
-    let map_size = map.get_size();
-    let mut candidates: HashSet<(usize, usize)>;
-    let mut visited = HashSet::<usize>::new();
-    let mut group_visited = HashSet::<usize>::new();
-
-    // If the number of remaining tiles is less than `GROUP_SIZE_LIMIT`,
-    // just compute the permutations as one group.
-    if map_size - map.get_tiles_flipped() < GROUP_SIZE_LIMIT as u32 {
-        let mut border_unflipped = HashSet::<usize>::new();
-
-        for i in 0..map_size as usize {
-            if !map.get_tile(i).flipped && !map.get_tile(i).flagged {
-                border_unflipped.insert(i);
-            }
-        }
-        candidates = evaluate_group(map, &border_unflipped);
-    } else {
-        candidates = HashSet::new();
-        // Loop over each tile and consider it's group.
-        for i in 0..map_size as usize {
-            // Skip flipped tiles and tiles that have already been considered.
-            if visited.contains(&i) || map.get_tile(i).flipped {
-                continue;
-            }
-
-            let groups: Vec<HashSet<usize>> =
-                recursive_border_search(map, i, &mut visited, &mut group_visited);
-
-            // Evaluate each group
-            for group in groups {
-                if group.len() < GROUP_SIZE_LIMIT {
-                    candidates.extend(evaluate_group(map, &group));
-                }
-            }
-        }
-    }
+/// Find every group and solve them independently, since tiles from separate groups
+/// won't affect each others solution. The certain moves from all groups are made in one pass.
+/// Unless `guess` is set, only certain moves are made.
+/// Produces the moves made along with whether the least risky tile was flipped as a guess.
+fn enumerate_groups(map: &mut Map, bitboard: &mut Bitboard, guess: bool) -> (VecDeque<Move>, bool) {
+    let candidates: HashSet<(usize, usize, i8)> = evaluate_groups(map, &find_groups(map, bitboard));
 
     let mut moves: VecDeque<Move> = VecDeque::new();
 
     // Sort the candidates
     let mut candidates_sorted = Vec::from_iter(candidates.iter());
-    candidates_sorted.sort_by(|a, b| a.1.cmp(&b.1));
+    candidates_sorted.sort_by_key(|a| (a.1, a.0));
 
-    let mut min_risk_tuple = (0, 0);
+    let mut min_risk_tuple = (0, 0, 0);
     let mut min_risk_tuple_found = false;
     for candidate in candidates_sorted {
         let position = point::from_index(candidate.0, map.get_width());
@@ -161,13 +367,9 @@ fn enumerate_groups(map: &mut Map) -> VecDeque<Move> {
                 position,
                 move_type: MoveType::Flip,
             });
-        // Certain mine.
+        // Certain mine, flagged once for each mine on the tile.
         } else if candidate.1 == 256 {
-            map.flag(&position);
-            moves.push_back(Move {
-                position,
-                move_type: MoveType::Flag,
-            });
+            flag_mines(map, &position, candidate.2, &mut moves);
         } else if !min_risk_tuple_found {
             min_risk_tuple = *candidate;
             min_risk_tuple_found = true;
@@ -176,109 +378,63 @@ fn enumerate_groups(map: &mut Map) -> VecDeque<Move> {
 
     // If no certain moves were made, do the least risky.
     let position = point::from_index(min_risk_tuple.0, map.get_width());
-    if moves.len() == 0 && min_risk_tuple_found {
+    if moves.is_empty() && min_risk_tuple_found && guess {
         map.flip(&position);
         moves.push_back(Move {
             position,
             move_type: MoveType::Flip,
         });
+        return (moves, true);
     }
 
-    moves
+    (moves, false)
 }
 
-/// Recursively search the for the border tiles of a cohesive group of tiles.
-/// A group of tiles is defined as a collection of tiles such that their configuration
-/// is solvable by considering only tiles within the group.
-fn recursive_border_search(
-    map: &Map,
-    index: usize,
-    visited: &mut HashSet<usize>,
-    mut group_visited: &mut HashSet<usize>,
-) -> Vec<HashSet<usize>> {
-    // Stop recursion if this tile is flipped, flagged or already visited.
-    if visited.contains(&index) || map.get_tile(index).flipped || map.get_tile(index).flagged {
-        return vec![];
-    }
-
-    // Add to visited list
-    visited.insert(index);
-
-    let mut found_borders: Vec<HashSet<usize>> = Vec::new();
-
-    // Loop over the neighbours to determine if this is a border tile and to recurse.
-    let neighbours: HashSet<Point> = point::get_neighbours(
-        &point::from_index(index, map.get_width()),
-        map.get_width(),
-        map.get_height(),
-    );
-    for neighbour in &neighbours {
-        let neighbour_index = neighbour.to_index(map.get_width());
-
-        // Skip this tile if it's already been visited.
-        if visited.contains(&neighbour_index) {
-            continue;
-        }
-
-        // Check if this is a border tile.
-        if map.get_tile(neighbour_index).flipped {
-            // Skip this tile if it's already in a group.
-            if group_visited.contains(&neighbour_index) {
-                continue;
-            }
-            // Find the full border group
-            let mut group_members: HashSet<usize> = HashSet::new();
-            recursive_border_grok_flipped(
-                map,
-                &mut group_visited,
-                &mut group_members,
-                neighbour_index,
-            );
-            found_borders.push(group_members);
-        } else {
-            // Continue the search
-            found_borders.append(&mut recursive_border_search(
-                map,
-                neighbour_index,
-                visited,
-                group_visited,
-            ));
-        }
+/// Find the groups of hidden tiles small enough to enumerate.
+/// If fewer tiles remain than the group size limit, they are all enumerated as one group.
+fn find_groups(map: &Map, bitboard: &mut Bitboard) -> Vec<HashSet<usize>> {
+    let group_size_limit = group_size_limit(mine_count_range(map).1);
+    if map.get_tile_count() - map.get_tiles_flipped() < group_size_limit as u32 {
+        let border_unflipped: HashSet<usize> = (0..map.get_size() as usize)
+            .filter(|i| {
+                map.tile_exists(*i) && !map.get_tile(*i).flipped && !map.get_tile(*i).is_flagged()
+            })
+            .collect();
+        return vec![border_unflipped];
     }
 
-    found_borders
+    bitboard.sync(map);
+    bitboard
+        .get_groups()
+        .iter()
+        .filter(|group| (group.count() as usize) < group_size_limit)
+        .map(|group| group.iter().collect())
+        .collect()
 }
 
 /// Recursively find all members of the group.
 fn recursive_border_grok_flipped(
     map: &Map,
     visited: &mut HashSet<usize>,
-    mut members: &mut HashSet<usize>,
+    members: &mut HashSet<usize>,
     flipped_index: usize,
 ) {
     // Loop over the neighbours of the flipped tile to find unflipped members of the group.
-    let neighbours: HashSet<Point> = point::get_neighbours(
-        &point::from_index(flipped_index, map.get_width()),
-        map.get_width(),
-        map.get_height(),
-    );
-    for neighbour in &neighbours {
-        let neighbour_index = neighbour.to_index(map.get_width());
-
+    for &neighbour_index in map.get_adjacent(flipped_index) {
         // Skip this tile if it's already been visited.
         if visited.contains(&neighbour_index) {
             continue;
         }
 
         // Check if this neighbour is unflipped and unflagged.
-        if !map.get_tile(neighbour_index).flipped && !map.get_tile(neighbour_index).flagged {
+        if !map.get_tile(neighbour_index).flipped && !map.get_tile(neighbour_index).is_flagged() {
             visited.insert(neighbour_index);
 
             // Add this neighbour to the group.
             members.insert(neighbour_index);
 
             // Recurse
-            recursive_border_grok_unflipped(map, visited, &mut members, neighbour_index);
+            recursive_border_grok_unflipped(map, visited, members, neighbour_index);
         }
     }
 }
@@ -287,18 +443,11 @@ fn recursive_border_grok_flipped(
 fn recursive_border_grok_unflipped(
     map: &Map,
     visited: &mut HashSet<usize>,
-    mut members: &mut HashSet<usize>,
+    members: &mut HashSet<usize>,
     unflipped_index: usize,
 ) {
     // Loop over the neighbours of the unflipped tile to find flipped members of the group.
-    let neighbours: HashSet<Point> = point::get_neighbours(
-        &point::from_index(unflipped_index, map.get_width()),
-        map.get_width(),
-        map.get_height(),
-    );
-    for neighbour in &neighbours {
-        let neighbour_index = neighbour.to_index(map.get_width());
-
+    for &neighbour_index in map.get_adjacent(unflipped_index) {
         // Skip this tile if it's already been visited.
         if visited.contains(&neighbour_index) {
             continue;
@@ -309,224 +458,1381 @@ fn recursive_border_grok_unflipped(
             visited.insert(neighbour_index);
 
             // Recurse
-            recursive_border_grok_flipped(map, visited, &mut members, neighbour_index);
+            recursive_border_grok_flipped(map, visited, members, neighbour_index);
         }
     }
 }
 
-/// Compute possible permutations within the given group to find tiles that either must
-/// be flagged or must be a mine. Produces a list of tile nominations with a risk value associated.
-fn evaluate_group(map: &mut Map, tiles_unflipped: &HashSet<usize>) -> HashSet<(usize, usize)> {
-    let mut staging_map: Map = map.clone();
-    let unflipped_count: usize = min(GROUP_SIZE_LIMIT, tiles_unflipped.len());
-    let max_mines: u32 = min(staging_map.get_mines_remaining(), unflipped_count as u32);
-    let mut tallies = HashMap::<usize, u32>::new();
+/// Evaluate each of the `groups`, spreading them across threads with the `parallel` feature.
+/// Produces the nominations from every group.
+fn evaluate_groups(map: &Map, groups: &[HashSet<usize>]) -> HashSet<(usize, usize, i8)> {
+    #[cfg(feature = "parallel")]
+    return groups
+        .par_iter()
+        .map(|group| evaluate_group(map, group))
+        .flatten_iter()
+        .collect();
+
+    #[cfg(not(feature = "parallel"))]
+    return groups
+        .iter()
+        .flat_map(|group| evaluate_group(map, group))
+        .collect();
+}
+
+/// The outcome of checking every permutation of mines within a group.
+struct GroupTallies {
+    /// The tiles of the group in index order, leaving out any beyond the group size limit.
+    tiles: Vec<usize>,
+    /// The number of valid permutations placing a mine on each tile.
+    tallies: Vec<u32>,
+    /// The mine counts seen on each tile, as a bitmask of their digits.
+    counts_seen: Vec<u32>,
+    valid_permutations: u32,
+}
+
+/// Compute possible permutations within the given group,
+/// counting how many of those that satisfy the flipped tiles around it place a mine on each tile.
+fn tally_group(map: &Map, tiles_unflipped: &HashSet<usize>) -> GroupTallies {
+    let capacity: u32 = map.get_tile_capacity() as u32;
+    let (min_count, radix) = mine_count_range(map);
+    let unflipped_count: usize = min(group_size_limit(radix), tiles_unflipped.len());
+    let max_mines: u32 = min(map.get_mines_remaining(), unflipped_count as u32 * capacity);
+    let mut tallies: Vec<u32> = vec![0; unflipped_count];
+    let mut counts_seen: Vec<u32> = vec![0; unflipped_count];
     let mut valid_permutations = 0;
-    let map_width = staging_map.get_width();
-    let mut tiles_flipped: HashSet<usize> = HashSet::new();
 
     // Sort so that results are deterministic.
-    let mut tiles_unflipped_sorted = Vec::from_iter(tiles_unflipped.iter());
+    let mut tiles_unflipped_sorted = Vec::from_iter(tiles_unflipped.iter().cloned());
     tiles_unflipped_sorted.sort();
+    tiles_unflipped_sorted.truncate(unflipped_count);
+    let positions: HashMap<usize, usize> = tiles_unflipped_sorted
+        .iter()
+        .enumerate()
+        .map(|(j, index)| (*index, j))
+        .collect();
 
     // Find all the neibouring flipped tiles.
+    let mut tiles_flipped: Vec<usize> = Vec::new();
     for index in &tiles_unflipped_sorted {
-        let neighbours: HashSet<Point> = point::get_neighbours(
-            &point::from_index(**index, map.get_width()),
-            map.get_width(),
-            map.get_height(),
-        );
-        for neighbour in neighbours {
-            let neighbour_index = neighbour.to_index(map.get_width());
+        for &neighbour_index in map.get_adjacent(*index) {
             if map.get_tile(neighbour_index).flipped {
-                tiles_flipped.insert(neighbour_index);
+                tiles_flipped.push(neighbour_index);
             }
         }
-
-        // Initialise flag tallies
-        tallies.insert(**index, 0);
     }
+    tiles_flipped.sort_unstable();
+    tiles_flipped.dedup();
 
-    // Initialise the valid flag tally map.
-    for index in tiles_unflipped {
-        tallies.insert(*index, 0);
-    }
+    let mut constraints: Vec<(i32, Vec<usize>)> =
+        build_constraints(map, &tiles_flipped, &positions);
+
+    // Permutations sharing every digit from a constraint's lowest member up break it alike,
+    // so check the constraints that can skip the most permutations first.
+    constraints.sort_by_key(|(_, members)| Reverse(members.iter().min().cloned()));
+    let lowest: Vec<u32> = constraints
+        .iter()
+        .map(|(_, members)| members.iter().min().map_or(0, |j| *j as u32))
+        .collect();
+
+    // With at most one mine per tile a permutation is a bitset of its mines,
+    // so each constraint can be checked a word at a time.
+    let binary: bool = radix == 2 && min_count == 0;
+    let masks: Vec<u32> = constraints
+        .iter()
+        .map(|(_, members)| members.iter().fold(0, |mask, j| mask | 1 << j))
+        .collect();
+
+    // Loop for each possible permutation of mine positions.
+    let mut digits: Vec<u32> = vec![0; unflipped_count];
+    let mut mines: Vec<i32> = vec![0; unflipped_count];
+    let permutations: u32 = radix.pow(unflipped_count as u32);
+    let mut i: u32 = 0;
+    'outer: while i < permutations {
+        // Use the permutation index as a number in base `radix`
+        // to determine how many mines are on each tile.
+        let total: u32 = if binary {
+            i.count_ones()
+        } else {
+            let mut remainder = i;
+            let mut total: u32 = 0;
+            for (digit, count) in digits.iter_mut().zip(mines.iter_mut()) {
+                *digit = remainder % radix;
+                *count = *digit as i32 + min_count;
+                remainder /= radix;
+                total += count.unsigned_abs();
+            }
+            total
+        };
 
-    // Loop for each possible permutation of flag positions.
-    'outer: for i in 0..(1 << unflipped_count) {
         // Skip early if this permutation contains too many mines.
-        if (i as usize).count_ones() > max_mines {
+        if total > max_mines {
+            i += 1;
             continue;
         }
 
-        let mut j: usize = 0;
-        for index in &tiles_unflipped_sorted {
-            // Use the permutation index to determine if this tile is flagged or not
-            // using i as a mitmask.
-            if i & (1 << j) > 0 {
-                if !staging_map.get_tile(**index).flagged {
-                    staging_map.flag(&point::from_index(**index, map_width));
-                }
+        // Check if the flipped tiles are satisfied by this permutation,
+        // skipping every following permutation that can't satisfy them either.
+        for (c, (needed, members)) in constraints.iter().enumerate() {
+            let sum: i32 = if binary {
+                (i & masks[c]).count_ones() as i32
             } else {
-                if staging_map.get_tile(**index).flagged {
-                    staging_map.flag(&point::from_index(**index, map_width));
-                }
-            }
-            j += 1;
-        }
-
-        // Check if the flipped tiles are satisfied by this permutation.
-        for index in &tiles_flipped {
-            if !staging_map.is_tile_satisfied(&point::from_index(*index, map_width)) {
+                members.iter().map(|j| mines[*j]).sum()
+            };
+            if sum != *needed {
+                let block: u32 = radix.pow(lowest[c]);
+                i = (i / block + 1) * block;
                 continue 'outer;
             }
         }
         valid_permutations += 1;
 
-        // Increment the valid flag tally for each unflipped tile.
-        for index in tiles_unflipped {
-            if staging_map.get_tile(*index).flagged {
-                let tally = tallies.entry(*index).or_insert(0);
-                *tally += 1;
+        // Increment the valid mine tally for each unflipped tile and note how many mines it held.
+        if binary {
+            for (j, tally) in tallies.iter_mut().enumerate() {
+                let digit: u32 = i >> j & 1;
+                *tally += digit;
+                counts_seen[j] |= 1 << digit;
+            }
+        } else {
+            for (j, count) in mines.iter().enumerate() {
+                if *count != 0 {
+                    tallies[j] += 1;
+                }
+                counts_seen[j] |= 1 << digits[j];
             }
         }
+        i += 1;
+    }
+
+    GroupTallies {
+        tiles: tiles_unflipped_sorted,
+        tallies,
+        counts_seen,
+        valid_permutations,
+    }
+}
+
+/// Find tiles within the given group that either must be flagged or must be a mine.
+/// Produces a list of tile nominations with a risk value associated,
+/// along with the number of mines on tiles that must be flagged.
+fn evaluate_group(map: &Map, tiles_unflipped: &HashSet<usize>) -> HashSet<(usize, usize, i8)> {
+    let GroupTallies {
+        tiles,
+        tallies,
+        counts_seen,
+        valid_permutations,
+    } = tally_group(map, tiles_unflipped);
+    let min_count: i32 = mine_count_range(map).0;
+    let mut nominations: HashSet<(usize, usize, i8)> = HashSet::new();
+
+    // No permutation fits, so nothing can be said about this group.
+    if valid_permutations == 0 {
+        return nominations;
     }
 
-    let mut nominations: HashSet<(usize, usize)> = HashSet::new();
     let mut min_index: usize = 0;
     let mut min_value: u32 = valid_permutations + 1;
-    for (index, tally) in tallies {
-        // Nominate all that never had a flag for flipping.
+    for (j, index) in tiles.iter().enumerate() {
+        let tally = tallies[j];
+        // Nominate all that never had a mine for flipping.
         if tally == 0 {
-            nominations.insert((index, 0));
-        // Nominate all that always had a flag for flagging.
+            nominations.insert((*index, 0, 0));
+        // Nominate all that always had the same number of mines for flagging.
         } else if tally == valid_permutations {
-            nominations.insert((index, 256));
+            if counts_seen[j].is_power_of_two() {
+                let count: i32 = counts_seen[j].trailing_zeros() as i32 + min_count;
+                nominations.insert((*index, 256, count as i8));
+            }
         } else if tally < min_value {
             min_value = tally;
-            min_index = index;
+            min_index = *index;
         }
     }
 
     // If no certain moves were found, nominate the least risky.
-    if nominations.len() == 0 && valid_permutations > 0 {
-        nominations.insert((min_index, (min_value * (255 / valid_permutations)) as usize));
+    // The risk is scaled to 1..=255, as a risk of 0 would be flipped as certainly safe.
+    if nominations.is_empty() && min_value <= valid_permutations {
+        let risk: u64 = min_value as u64 * 255 / valid_permutations as u64;
+        nominations.insert((min_index, risk.max(1) as usize, 0));
     }
 
     nominations
 }
 
-/// Perform a random move
-fn random_move(map: &mut Map) -> Move {
-    let random_index: usize =
-        rand::random::<usize>() % (map.get_size() - map.get_tiles_flipped()) as usize;
+/// Get the chance that each tile holds a mine as the solver sees it when looking for moves,
+/// from the tallies of the layouts of each group that fit the flipped tiles around it.
+/// Unlike `mine_probabilities`, each group is weighed on its own without the number of mines remaining.
+/// Tiles outside every group, including those of groups too large to enumerate, have no estimate,
+/// nor do flipped or flagged tiles.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// // A long strip with a mine on the end, and a clue revealed beside it.
+/// let mines: HashSet<point::Point> = [point::Point { x: 29, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(30, 1, mines);
+/// map.reveal(&point::Point { x: 28, y: 0 });
+///
+/// // Either side of the clue could be the mine, and the rest of the strip is outside the group.
+/// let probabilities = solver::group_probabilities(&map);
+/// assert_eq!(vec![Some(0.5), None, Some(0.5)], probabilities[27..]);
+/// assert_eq!(None, probabilities[0]);
+/// ```
+pub fn group_probabilities(map: &Map) -> Vec<Option<f64>> {
+    let mut probabilities: Vec<Option<f64>> = vec![None; map.get_tiles().len()];
+    if *map.get_status() != Status::InProgress {
+        return probabilities;
+    }
 
-    let mut unflipped_index: usize = 0;
-    for i in 0..map.get_tiles().len() {
-        if !map.get_tile(i).flipped {
-            if unflipped_index == random_index {
-                let position = point::from_index(i, map.get_width());
-                map.flip(&position);
-                return Move {
-                    position,
-                    move_type: MoveType::Flip,
-                };
-            }
-            unflipped_index += 1;
+    let mut bitboard = Bitboard::from_map(map);
+    for group in find_groups(map, &mut bitboard) {
+        let tallies = tally_group(map, &group);
+        if tallies.valid_permutations == 0 {
+            continue;
+        }
+        for (j, index) in tallies.tiles.iter().enumerate() {
+            probabilities[*index] =
+                Some(tallies.tallies[j] as f64 / tallies.valid_permutations as f64);
         }
     }
-    panic!("Failed to find a random tile.");
+    probabilities
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{map, solver};
-
-    #[test]
-    fn test_simple_solve() {
-        // Define mine positions
-        let mines: HashSet<point::Point> = [
-            point::Point { x: 3, y: 1 },
-            point::Point { x: 4, y: 2 },
-            point::Point { x: 1, y: 1 },
-            point::Point { x: 2, y: 2 },
-            point::Point { x: 4, y: 4 },
-        ]
+/// Each flipped tile constrains the tiles around it to hold the mines it still needs.
+/// Produces the number of mines needed by each of the `tiles_flipped`, along with the
+/// neighbours it constrains, given by their `positions` in the group.
+fn build_constraints(
+    map: &Map,
+    tiles_flipped: &[usize],
+    positions: &HashMap<usize, usize>,
+) -> Vec<(i32, Vec<usize>)> {
+    tiles_flipped
         .iter()
-        .cloned()
-        .collect();
+        .map(|index| {
+            let mut needed: i32 = map.get_tile(*index).value as i32;
+            let mut members: Vec<usize> = Vec::new();
+            for neighbour_index in map.get_adjacent(*index) {
+                let neighbour: &Tile = map.get_tile(*neighbour_index);
+                needed -= neighbour.flags as i32;
+                if neighbour.flipped {
+                    needed -= neighbour.mines as i32;
+                }
+                if let Some(j) = positions.get(neighbour_index) {
+                    members.push(*j);
+                }
+            }
+            (needed, members)
+        })
+        .collect()
+}
 
-        // Generate map with these mines.
-        let mut map = map::generate_map_with_mines(5, 5, mines);
+/// Count the full mine layouts consistent with every flipped tile of the `map`
+/// and its remaining number of mines, treating flags as placed mines.
+/// Tiles out of reach of every clue are counted combinatorially, so the count covers the whole board.
+/// Counts above the `cap` are reported as the `cap`, a count of 1 means the position is fully determined.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// // A 3x1 map with a mine on the end.
+/// let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(3, 1, mines);
+/// assert_eq!(3, solver::count_solutions(&map, u128::MAX));
+///
+/// // The middle clue leaves the mine on either end.
+/// map.flip(&point::Point { x: 1, y: 0 });
+/// assert_eq!(2, solver::count_solutions(&map, u128::MAX));
+/// assert_eq!(1, solver::count_solutions(&map, 1));
+/// ```
+pub fn count_solutions(map: &Map, cap: u128) -> u128 {
+    count_restricted_solutions(map, cap, None)
+}
 
-        // Flip and flag a couple of tiles.
-        map.flip(&point::Point { x: 0, y: 4 });
-        map.flag(&point::Point { x: 3, y: 1 });
+/// Count solutions like `count_solutions`, optionally restricting a tile to be safe or a mine,
+/// given as its index and whether it is `mined`.
+fn count_restricted_solutions(map: &Map, cap: u128, restriction: Option<(usize, bool)>) -> u128 {
+    let mut frontier = match Frontier::new(map, restriction) {
+        Some(frontier) => frontier,
+        None => return 0,
+    };
+    let remaining: usize = frontier.remaining;
+    let interior_ways = count_interior_ways(frontier.interior.len(), remaining, map);
+
+    // Groups share no clues, so count the ways each group can hold every number of mines
+    // and combine them. Capping every count along the way leaves the capped total unchanged.
+    // The largest group is left until last so that its search can stop at the cap.
+    let last: Option<usize> = (0..frontier.groups.len()).max_by_key(|g| frontier.groups[*g].len());
+    let mut totals: Vec<u128> = vec![0; remaining + 1];
+    totals[0] = 1;
+    for g in (0..frontier.groups.len()).filter(|g| Some(*g) != last) {
+        let mut ways: Vec<u128> = vec![0; frontier.get_group_max_mines(g) + 1];
+        frontier.search(g, &mut |used, _| ways[used] = min(cap, ways[used] + 1));
+        totals = convolve(&totals, &ways, cap);
+        if totals.iter().all(|count| *count == 0) {
+            return 0;
+        }
+    }
 
-        // Map should be in progrsss
-        assert_eq!(map::Status::InProgress, *map.get_status());
+    // Weigh each number of mines in the last group by the ways to place the rest.
+    let weights: Vec<u128> = (0..=remaining)
+        .map(|mines| {
+            let mut weight: u128 = 0;
+            for (used, ways) in totals.iter().enumerate().take(remaining + 1 - mines) {
+                weight = weight
+                    .saturating_add(ways.saturating_mul(interior_ways[remaining - used - mines]));
+            }
+            min(weight, cap)
+        })
+        .collect();
+    let g: usize = match last {
+        Some(g) => g,
+        None => return weights[0],
+    };
+
+    let mut count: u128 = 0;
+    frontier.search_until(g, &mut |used, _| {
+        count = min(cap, count.saturating_add(weights[used]));
+        count >= cap
+    });
+    count
+}
 
-        // Solve the map.
-        let moves = solver::solve(&map);
+/// Draw a full mine layout uniformly at random from those consistent with every flipped tile
+/// of the `map` and its remaining number of mines, treating flags as placed mines.
+/// Produces the positions holding mines, see `sample_consistent_mine_counts` for how many.
+/// Produces nothing if no layout is consistent with the map, such as when a flag sits on a safe tile.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(4, 1, mines);
+/// map.flip(&point::Point { x: 0, y: 0 });
+///
+/// // Only the last two tiles could hold the mine.
+/// let layout = solver::sample_consistent_layout(&map, &mut rand::thread_rng());
+/// assert_eq!(1, layout.len());
+/// assert!(layout.iter().all(|mine| mine.x >= 2));
+/// ```
+pub fn sample_consistent_layout<R: Rng + ?Sized>(map: &Map, rng: &mut R) -> HashSet<Point> {
+    sample_consistent_mine_counts(map, rng)
+        .into_iter()
+        .filter(|(_, count)| *count != 0)
+        .map(|(position, _)| position)
+        .collect()
+}
 
-        // Should have taken 14 moves.
-        assert_eq!(14, moves.len());
+/// Draw a full mine layout uniformly at random like `sample_consistent_layout`,
+/// producing the number of mines on each mined tile, negative for negative mines.
+pub fn sample_consistent_mine_counts<R: Rng + ?Sized>(
+    map: &Map,
+    rng: &mut R,
+) -> HashMap<Point, i8> {
+    match sample_mine_counts(map, rng, None) {
+        Some(counts) => counts,
+        None => panic!("No mine layout is consistent with the map."),
+    }
+}
 
-        // Apply the moves to the map.
-        map.apply_moves(&moves);
+/// Draw a full mine layout uniformly at random like `sample_consistent_mine_counts`,
+/// from only the layouts where the tile at `index` is a mine if `mined` is set, or safe otherwise.
+/// Produces `None` if no consistent layout gives the tile that outcome.
+pub(crate) fn sample_restricted_mine_counts<R: Rng + ?Sized>(
+    map: &Map,
+    rng: &mut R,
+    index: usize,
+    mined: bool,
+) -> Option<HashMap<Point, i8>> {
+    if count_restricted_solutions(map, 1, Some((index, mined))) == 0 {
+        return None;
+    }
+    sample_mine_counts(map, rng, Some((index, mined)))
+}
 
-        // Map should be completed
-        assert_eq!(map::Status::Complete, *map.get_status());
+fn sample_mine_counts<R: Rng + ?Sized>(
+    map: &Map,
+    rng: &mut R,
+    restriction: Option<(usize, bool)>,
+) -> Option<HashMap<Point, i8>> {
+    let mut frontier = Frontier::new(map, restriction)?;
+    let remaining: usize = frontier.remaining;
+
+    // Weigh each number of mines for every group, and every prefix of the groups combined.
+    // Weights are kept as logarithms since the number of layouts of a large board overflows.
+    let mut group_ways: Vec<Vec<f64>> = Vec::new();
+    let mut prefixes: Vec<Vec<f64>> = vec![vec![f64::NEG_INFINITY; remaining + 1]];
+    prefixes[0][0] = 0.0;
+    for g in 0..frontier.groups.len() {
+        let mut ways: Vec<f64> = vec![0.0; frontier.get_group_max_mines(g) + 1];
+        frontier.search(g, &mut |used, _| ways[used] += 1.0);
+        let ways: Vec<f64> = ways.iter().map(|count| count.ln()).collect();
+
+        let mut combined: Vec<f64> = vec![f64::NEG_INFINITY; remaining + 1];
+        for (i, x) in prefixes[g].iter().enumerate() {
+            for (j, y) in ways.iter().enumerate().take(remaining + 1 - i) {
+                combined[i + j] = ln_add(combined[i + j], x + y);
+            }
+        }
+        group_ways.push(ways);
+        prefixes.push(combined);
     }
 
-    #[test]
-    fn test_hard_solve() {
-        // Define mine positions.
-        let mines: HashSet<point::Point> = [
-            point::Point { x: 0, y: 0 },
-            point::Point { x: 1, y: 0 },
-            point::Point { x: 2, y: 0 },
-            point::Point { x: 3, y: 1 },
-            point::Point { x: 4, y: 1 },
-            point::Point { x: 5, y: 1 },
-            point::Point { x: 7, y: 1 },
-            point::Point { x: 8, y: 1 },
-            point::Point { x: 2, y: 2 },
-            point::Point { x: 6, y: 2 },
-            point::Point { x: 9, y: 2 },
-            point::Point { x: 0, y: 4 },
-            point::Point { x: 2, y: 5 },
-            point::Point { x: 4, y: 5 },
-            point::Point { x: 5, y: 5 },
-            point::Point { x: 8, y: 5 },
-            point::Point { x: 9, y: 5 },
-            point::Point { x: 1, y: 6 },
-            point::Point { x: 9, y: 6 },
-            point::Point { x: 0, y: 7 },
-            point::Point { x: 1, y: 7 },
-            point::Point { x: 4, y: 7 },
-            point::Point { x: 4, y: 8 },
-            point::Point { x: 3, y: 9 },
-            point::Point { x: 8, y: 9 },
-        ]
-        .iter()
-        .cloned()
-        .collect();
+    // With one mine per tile the interior can be filled by picking tiles, otherwise
+    // the ways to fill each number of interior tiles are needed to fill them one at a time.
+    let simple: bool = map.get_tile_capacity() == 1 && !map.has_negative_mines();
+    let interior_ways = weigh_interior_ways(frontier.interior.len(), remaining, map, !simple);
+    let interior_total = &interior_ways[interior_ways.len() - 1];
+
+    // Choose how many mines are next to clues, then how many each group holds, working backwards.
+    let last = &prefixes[frontier.groups.len()];
+    let mut border_mines: usize = choose_weighted(
+        rng,
+        (0..=remaining).map(|used| last[used] + interior_total[remaining - used]),
+    );
+    let mut group_mines: Vec<usize> = vec![0; frontier.groups.len()];
+    for g in (0..frontier.groups.len()).rev() {
+        let mines: usize = choose_weighted(
+            rng,
+            (0..=border_mines).map(|k| {
+                group_ways[g].get(k).unwrap_or(&f64::NEG_INFINITY) + prefixes[g][border_mines - k]
+            }),
+        );
+        group_mines[g] = mines;
+        border_mines -= mines;
+    }
 
-        // Create a map with these mines.
-        let mut map = map::generate_map_with_mines(10, 10, mines);
+    let mut counts: HashMap<Point, i8> = HashMap::new();
+    let width: u16 = map.get_width();
 
-        // Flip a safe tile.
-        map.flip(&point::Point { x: 6, y: 9 });
+    // Flagged and flipped tiles keep the mines they are known to hold.
+    for i in 0..map.get_tiles().len() {
+        let tile: &Tile = map.get_tile(i);
+        if tile.is_flagged() && !tile.flipped {
+            counts.insert(point::from_index(i, width), tile.flags);
+        } else if tile.flipped && tile.is_mine() {
+            counts.insert(point::from_index(i, width), tile.mines);
+        }
+    }
 
-        // Solve the map.
-        let moves = solver::solve(&map);
+    // Pick one of the layouts of each group with the chosen number of mines, by reservoir sampling.
+    let mut interior_mines: usize = remaining;
+    for (g, target) in group_mines.iter().enumerate() {
+        let mut seen: u64 = 0;
+        let mut chosen: Vec<i32> = Vec::new();
+        frontier.search(g, &mut |used, assignment| {
+            if used == *target {
+                seen += 1;
+                if rng.gen_range(0..seen) == 0 {
+                    chosen = assignment.to_vec();
+                }
+            }
+        });
+        for j in &frontier.groups[g] {
+            if chosen[*j] != 0 {
+                counts.insert(
+                    point::from_index(frontier.border[*j], width),
+                    chosen[*j] as i8,
+                );
+            }
+        }
+        interior_mines -= target;
+    }
 
-        // Apply the moves to the map.
-        map.apply_moves(&moves);
+    if simple {
+        for index in frontier.interior.choose_multiple(rng, interior_mines) {
+            counts.insert(point::from_index(*index, width), 1);
+        }
+        return Some(counts);
+    }
 
-        // Map should be solved.
+    // Spread the rest over the interior one tile at a time, weighing each count
+    // by the ways the remaining tiles can hold the mines left over.
+    let capacity: i32 = map.get_tile_capacity() as i32;
+    let (min_count, _) = mine_count_range(map);
+    for (t, index) in frontier.interior.iter().enumerate() {
+        let rest = &interior_ways[frontier.interior.len() - t - 1];
+        let choices: Vec<i32> = (min_count..=capacity)
+            .filter(|count| count.unsigned_abs() as usize <= interior_mines)
+            .collect();
+        let choice: usize = choose_weighted(
+            rng,
+            choices
+                .iter()
+                .map(|count| rest[interior_mines - count.unsigned_abs() as usize]),
+        );
+        let count: i32 = choices[choice];
+        if count != 0 {
+            counts.insert(point::from_index(*index, width), count as i8);
+        }
+        interior_mines -= count.unsigned_abs() as usize;
+    }
+
+    Some(counts)
+}
+
+/// Add two numbers given as natural logarithms, producing the logarithm of the sum.
+fn ln_add(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    if b == f64::NEG_INFINITY {
+        return a;
+    }
+    let max: f64 = a.max(b);
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+/// Choose an index at random with probability proportional to its weight,
+/// given as a natural logarithm.
+fn choose_weighted<R: Rng + ?Sized>(rng: &mut R, weights: impl Iterator<Item = f64>) -> usize {
+    let weights: Vec<f64> = weights.collect();
+    let max: f64 = weights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        panic!("No mine layout is consistent with the map.");
+    }
+    let weights: Vec<f64> = weights.iter().map(|weight| (weight - max).exp()).collect();
+    let total: f64 = weights.iter().sum();
+
+    let mut target: f64 = rng.gen::<f64>() * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if *weight > 0.0 && target < *weight {
+            return i;
+        }
+        target -= weight;
+    }
+    // Rounding can leave the target just past the end, so fall back to the last possible choice.
+    weights.iter().rposition(|weight| *weight > 0.0).unwrap()
+}
+
+/// The hidden tiles of a map split into groups that share clues and the interior out of reach
+/// of every clue, along with the constraints the clues place on the groups.
+struct Frontier {
+    min_count: i32,
+    max_count: i32,
+    /// The number of mines left to place.
+    remaining: usize,
+    /// The indices of hidden tiles next to a clue.
+    border: Vec<usize>,
+    /// The indices of hidden tiles out of reach of every clue.
+    interior: Vec<usize>,
+    /// The positions in `border` of each group, in the order they are searched.
+    groups: Vec<Vec<usize>>,
+    /// The constraints touching each border tile.
+    tile_constraints: Vec<Vec<usize>>,
+    /// The mines each constraint still needs.
+    needed: Vec<i32>,
+    /// The number of tiles each constraint is still waiting on.
+    unassigned: Vec<i32>,
+    /// The mines placed on each border tile so far.
+    assignment: Vec<i32>,
+    /// A border tile restricted to being a mine if set, or safe otherwise.
+    restriction: Option<(usize, bool)>,
+}
+
+impl Frontier {
+    /// Build the frontier of the `map`, or `None` if a clue can't be satisfied.
+    /// A `restriction` on a tile, given as its index and whether it is mined, limits its layouts.
+    fn new(map: &Map, restriction: Option<(usize, bool)>) -> Option<Frontier> {
+        let (min_count, radix) = mine_count_range(map);
+
+        // Find the groups of hidden tiles connected through clues.
+        let mut visited = HashSet::<usize>::new();
+        let mut border: Vec<usize> = Vec::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut interior: Vec<usize> = Vec::new();
+        for i in 0..map.get_tiles().len() {
+            let tile: &Tile = map.get_tile(i);
+            if !map.tile_exists(i) || tile.flipped || tile.is_flagged() || visited.contains(&i) {
+                continue;
+            }
+            // A restricted tile is searched even if no clue can see it.
+            let restricted: bool = restriction.map(|(index, _)| index) == Some(i);
+            if !restricted
+                && !map
+                    .get_adjacent(i)
+                    .iter()
+                    .any(|neighbour| map.get_tile(*neighbour).flipped)
+            {
+                interior.push(i);
+                continue;
+            }
+
+            let mut members: HashSet<usize> = HashSet::new();
+            visited.insert(i);
+            members.insert(i);
+            recursive_border_grok_unflipped(map, &mut visited, &mut members, i);
+
+            let mut members = Vec::from_iter(members);
+            members.sort_unstable();
+            groups.push((border.len()..border.len() + members.len()).collect());
+            border.append(&mut members);
+        }
+        let positions: HashMap<usize, usize> = border
+            .iter()
+            .enumerate()
+            .map(|(j, index)| (*index, j))
+            .collect();
+
+        let tiles_flipped: Vec<usize> = (0..map.get_tiles().len())
+            .filter(|i| map.get_tile(*i).flipped && !map.get_tile(*i).is_mine())
+            .collect();
+        let constraints = build_constraints(map, &tiles_flipped, &positions);
+
+        // A clue with nothing left to place its mines on can't be satisfied.
+        if constraints
+            .iter()
+            .any(|(needed, members)| members.is_empty() && *needed != 0)
+        {
+            return None;
+        }
+
+        let mut tile_constraints: Vec<Vec<usize>> = vec![vec![]; border.len()];
+        for (c, (_, members)) in constraints.iter().enumerate() {
+            for j in members {
+                tile_constraints[*j].push(c);
+            }
+        }
+
+        // Order each group breadth first so that clues are completed early in the search.
+        for group in groups.iter_mut() {
+            let mut order: Vec<usize> = vec![group[0]];
+            let mut queued: HashSet<usize> = order.iter().cloned().collect();
+            let mut next: usize = 0;
+            while next < order.len() {
+                let j: usize = order[next];
+                next += 1;
+                for c in &tile_constraints[j] {
+                    for member in &constraints[*c].1 {
+                        if queued.insert(*member) {
+                            order.push(*member);
+                        }
+                    }
+                }
+            }
+            *group = order;
+        }
+
+        Some(Frontier {
+            min_count,
+            max_count: min_count + radix as i32 - 1,
+            remaining: map.get_mines_remaining() as usize,
+            assignment: vec![0; border.len()],
+            restriction: restriction
+                .and_then(|(index, mined)| positions.get(&index).map(|j| (*j, mined))),
+            border,
+            interior,
+            groups,
+            tile_constraints,
+            needed: constraints.iter().map(|(needed, _)| *needed).collect(),
+            unassigned: constraints
+                .iter()
+                .map(|(_, members)| members.len() as i32)
+                .collect(),
+        })
+    }
+
+    /// Get the most mines the group `g` could hold.
+    fn get_group_max_mines(&self, g: usize) -> usize {
+        min(
+            self.remaining,
+            self.groups[g].len() * max(self.min_count.abs(), self.max_count) as usize,
+        )
+    }
+
+    /// Visit every layout of the group `g` that satisfies its clues, passing the number of mines
+    /// used and the mines on each border tile to `visit`.
+    fn search(&mut self, g: usize, visit: &mut dyn FnMut(usize, &[i32])) {
+        self.search_until(g, &mut |used, assignment| {
+            visit(used, assignment);
+            false
+        });
+    }
+
+    /// Visit layouts of the group `g` like `search`, stopping once `visit` returns true.
+    fn search_until(&mut self, g: usize, visit: &mut dyn FnMut(usize, &[i32]) -> bool) {
+        self.search_recurse(g, 0, 0, visit);
+    }
+
+    /// Search the rest of the group `g` from `depth`, returning true once the search should stop.
+    fn search_recurse(
+        &mut self,
+        g: usize,
+        depth: usize,
+        used: usize,
+        visit: &mut dyn FnMut(usize, &[i32]) -> bool,
+    ) -> bool {
+        if depth == self.groups[g].len() {
+            return visit(used, &self.assignment);
+        }
+
+        let j: usize = self.groups[g][depth];
+        for count in self.min_count..=self.max_count {
+            let mines: usize = count.unsigned_abs() as usize;
+            if used + mines > self.remaining {
+                continue;
+            }
+            if let Some((restricted, mined)) = self.restriction {
+                if restricted == j && mined != (count != 0) {
+                    continue;
+                }
+            }
+
+            // Place the mines and check each clue can still be satisfied by the tiles left.
+            let mut feasible: bool = true;
+            for c in self.tile_constraints[j].iter() {
+                self.needed[*c] -= count;
+                self.unassigned[*c] -= 1;
+                let needed: i32 = self.needed[*c];
+                let unassigned: i32 = self.unassigned[*c];
+                if needed < unassigned * self.min_count || needed > unassigned * self.max_count {
+                    feasible = false;
+                }
+            }
+            let mut stopped: bool = false;
+            if feasible {
+                self.assignment[j] = count;
+                stopped = self.search_recurse(g, depth + 1, used + mines, visit);
+            }
+            for c in self.tile_constraints[j].iter() {
+                self.needed[*c] += count;
+                self.unassigned[*c] += 1;
+            }
+            if stopped {
+                self.assignment[j] = 0;
+                return true;
+            }
+        }
+        self.assignment[j] = 0;
+        false
+    }
+}
+
+/// Combine the ways two independent sets of tiles can hold each number of mines,
+/// saturating at the `cap`.
+fn convolve(a: &[u128], b: &[u128], cap: u128) -> Vec<u128> {
+    let mut combined: Vec<u128> = vec![0; a.len()];
+    for (i, x) in a.iter().enumerate() {
+        if *x == 0 {
+            continue;
+        }
+        for (j, y) in b.iter().enumerate().take(a.len() - i) {
+            combined[i + j] = min(cap, combined[i + j].saturating_add(x.saturating_mul(*y)));
+        }
+    }
+    combined
+}
+
+/// Count the ways to spread each number of mines over `interior` tiles that no clue can see,
+/// up to the `remaining` number of mines.
+fn count_interior_ways(interior: usize, remaining: usize, map: &Map) -> Vec<u128> {
+    let capacity: usize = map.get_tile_capacity() as usize;
+    // Negative mines can be placed either way round.
+    let signs: u128 = if map.has_negative_mines() { 2 } else { 1 };
+
+    let mut ways: Vec<u128> = vec![0; remaining + 1];
+    ways[0] = 1;
+    for _ in 0..interior {
+        let mut next: Vec<u128> = vec![0; remaining + 1];
+        for (total, count) in ways.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            next[total] = next[total].saturating_add(*count);
+            for mines in 1..=min(capacity, remaining - total) {
+                next[total + mines] =
+                    next[total + mines].saturating_add(count.saturating_mul(signs));
+            }
+        }
+        ways = next;
+    }
+
+    ways
+}
+
+/// Weigh the ways to spread each number of mines over `n` tiles that no clue can see,
+/// as natural logarithms, up to the `remaining` number of mines.
+/// Produces a row for every `n` up to `interior` if `all_rows` is set, otherwise only the last.
+fn weigh_interior_ways(
+    interior: usize,
+    remaining: usize,
+    map: &Map,
+    all_rows: bool,
+) -> Vec<Vec<f64>> {
+    let capacity: usize = map.get_tile_capacity() as usize;
+
+    // With one mine per tile the ways are binomial coefficients.
+    if capacity == 1 && !map.has_negative_mines() && !all_rows {
+        let mut ways: Vec<f64> = vec![f64::NEG_INFINITY; remaining + 1];
+        let mut ln_choose: f64 = 0.0;
+        for (mines, way) in ways
+            .iter_mut()
+            .enumerate()
+            .take(min(interior, remaining) + 1)
+        {
+            *way = ln_choose;
+            ln_choose += ((interior - mines) as f64).ln() - ((mines + 1) as f64).ln();
+        }
+        return vec![ways];
+    }
+
+    // Negative mines can be placed either way round.
+    let signs: f64 = if map.has_negative_mines() {
+        2f64.ln()
+    } else {
+        0.0
+    };
+
+    let mut ways: Vec<Vec<f64>> = vec![vec![f64::NEG_INFINITY; remaining + 1]];
+    ways[0][0] = 0.0;
+    for _ in 0..interior {
+        let last = &ways[ways.len() - 1];
+        let mut next: Vec<f64> = vec![f64::NEG_INFINITY; remaining + 1];
+        for (total, count) in last.iter().enumerate() {
+            if *count == f64::NEG_INFINITY {
+                continue;
+            }
+            next[total] = ln_add(next[total], *count);
+            for mines in 1..=min(capacity, remaining - total) {
+                next[total + mines] = ln_add(next[total + mines], count + signs);
+            }
+        }
+        if !all_rows {
+            ways.clear();
+        }
+        ways.push(next);
+    }
+
+    ways
+}
+
+/// Get the chance that each tile holds a mine, over every full mine layout consistent with
+/// the flipped tiles and remaining number of mines, treating flags as placed mines.
+/// Flipped and masked tiles have no chance of holding a mine, and flagged tiles are certain to.
+/// Panics if no layout is consistent with the map.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(3, 1, mines);
+/// map.flip(&point::Point { x: 1, y: 0 });
+/// assert_eq!(Some(vec![0.5, 0.0, 0.5]), solver::mine_probabilities(&map));
+/// ```
+pub fn mine_probabilities(map: &Map) -> Option<Vec<f64>> {
+    let mut frontier = Frontier::new(map, None)?;
+    let remaining: usize = frontier.remaining;
+    let group_count: usize = frontier.groups.len();
+
+    // Count the layouts of each group by number of mines, and how many of them mine each tile.
+    let mut group_ways: Vec<Vec<f64>> = Vec::new();
+    let mut group_tallies: Vec<Vec<Vec<f64>>> = Vec::new();
+    for g in 0..group_count {
+        let max_mines: usize = frontier.get_group_max_mines(g);
+        let members: Vec<usize> = frontier.groups[g].clone();
+        let mut ways: Vec<f64> = vec![0.0; max_mines + 1];
+        let mut tallies: Vec<Vec<f64>> = vec![vec![0.0; members.len()]; max_mines + 1];
+        frontier.search(g, &mut |used, assignment| {
+            ways[used] += 1.0;
+            for (m, j) in members.iter().enumerate() {
+                if assignment[*j] != 0 {
+                    tallies[used][m] += 1.0;
+                }
+            }
+        });
+        group_ways.push(ways.iter().map(|count| count.ln()).collect());
+        group_tallies.push(tallies);
+    }
+
+    // Combine the groups before and after each group, as logarithms, with the interior after the last.
+    let interior: usize = frontier.interior.len();
+    let interior_ways = weigh_interior_ways(interior, remaining, map, false).remove(0);
+    let mut prefixes: Vec<Vec<f64>> = vec![vec![f64::NEG_INFINITY; remaining + 1]];
+    prefixes[0][0] = 0.0;
+    for g in 0..group_count {
+        let next = ln_convolve(&prefixes[g], &group_ways[g]);
+        prefixes.push(next);
+    }
+    let mut suffixes: Vec<Vec<f64>> = vec![interior_ways.clone()];
+    for g in (0..group_count).rev() {
+        let next = ln_convolve(&suffixes[0], &group_ways[g]);
+        suffixes.insert(0, next);
+    }
+    let total: f64 = suffixes[0][remaining];
+    if total == f64::NEG_INFINITY {
+        return None;
+    }
+
+    let mut probabilities: Vec<f64> = vec![0.0; map.get_tiles().len()];
+    for (i, tile) in map.get_tiles().iter().enumerate() {
+        if tile.is_flagged() && !tile.flipped {
+            probabilities[i] = 1.0;
+        }
+    }
+
+    // Weigh each layout of a group by the ways the rest of the board can hold the other mines.
+    for g in 0..group_count {
+        for (k, tallies) in group_tallies[g].iter().enumerate() {
+            if k > remaining {
+                break;
+            }
+            let mut others: f64 = f64::NEG_INFINITY;
+            for (a, prefix) in prefixes[g].iter().enumerate().take(remaining - k + 1) {
+                others = ln_add(others, prefix + suffixes[g + 1][remaining - k - a]);
+            }
+            let weight: f64 = (others - total).exp();
+            for (m, j) in frontier.groups[g].iter().enumerate() {
+                probabilities[frontier.border[*j]] += tallies[m] * weight;
+            }
+        }
+    }
+
+    // Every interior tile is equally likely to hold a mine, and is empty as often as
+    // one fewer tile can hold the mines left for the interior.
+    if interior > 0 {
+        let fewer_ways = weigh_interior_ways(interior - 1, remaining, map, false).remove(0);
+        let mut empty: f64 = 0.0;
+        for (used, prefix) in prefixes[group_count].iter().enumerate() {
+            let mines: usize = remaining - used;
+            empty += (prefix + fewer_ways[mines] - total).exp();
+        }
+        for index in &frontier.interior {
+            probabilities[*index] = (1.0 - empty).clamp(0.0, 1.0);
+        }
+    }
+
+    Some(probabilities)
+}
+
+/// Combine the ways two independent sets of tiles can hold each number of mines,
+/// given as logarithms, up to the length of `a`.
+fn ln_convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut combined: Vec<f64> = vec![f64::NEG_INFINITY; a.len()];
+    for (i, x) in a.iter().enumerate() {
+        if *x == f64::NEG_INFINITY {
+            continue;
+        }
+        for (j, y) in b.iter().enumerate().take(a.len() - i) {
+            combined[i + j] = ln_add(combined[i + j], x + y);
+        }
+    }
+    combined
+}
+
+/// Flip the tile with the best chance of being safe, favouring tiles whose hidden neighbours
+/// are all likely to be safe too since flipping them opens up the board.
+/// Produces the move along with the chance the tile held a mine,
+/// or nothing if no mine layout is consistent with the map.
+fn win_probability_move(map: &mut Map) -> Option<(Move, f64)> {
+    let probabilities: Vec<f64> = mine_probabilities(map)?;
+
+    let mut best: Option<(usize, f64)> = None;
+    for (i, probability) in probabilities.iter().enumerate() {
+        let tile: &Tile = map.get_tile(i);
+        if !map.tile_exists(i) || tile.flipped || tile.is_flagged() {
+            continue;
+        }
+
+        let mut opening: f64 = 1.0;
+        for neighbour in map.get_adjacent(i) {
+            if !map.get_tile(*neighbour).flipped {
+                opening *= 1.0 - probabilities[*neighbour];
+            }
+        }
+        let score: f64 = (1.0 - probability) * (1.0 + OPENING_WEIGHT * opening);
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((i, score));
+        }
+    }
+
+    let index: usize = match best {
+        Some((index, _)) => index,
+        None => panic!("Failed to find a tile to guess."),
+    };
+    let position = point::from_index(index, map.get_width());
+    map.flip(&position);
+    let play = Move {
+        position,
+        move_type: MoveType::Flip,
+    };
+    Some((play, probabilities[index]))
+}
+
+/// Search every way the game could play out from a position with few hidden tiles,
+/// to find the flip with the best chance of winning when playing perfectly from then on.
+/// Produces the tile to flip along with the chance of winning, treating flags as placed mines.
+/// Produces `None` if the map is finished, has too many hidden tiles to search or if no mine layout
+/// is consistent with it. Up to 12 hidden tiles are searched when each holds at most one mine,
+/// and fewer when tiles can hold more.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// // A 3x1 map with a mine on the end leaves a coin flip once the middle is flipped.
+/// let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+/// let mut map = map::generate_map_with_mines(3, 1, mines);
+/// map.flip(&point::Point { x: 1, y: 0 });
+/// let (_, probability) = solver::solve_endgame(&map).unwrap();
+/// assert_eq!(0.5, probability);
+/// ```
+pub fn solve_endgame(map: &Map) -> Option<(Point, f64)> {
+    endgame_move(map).map(|(position, probability, _)| (position, probability))
+}
+
+/// Find the best flip like `solve_endgame`, also producing whether the tile is certain to be safe.
+fn endgame_move(map: &Map) -> Option<(Point, f64, bool)> {
+    if *map.get_status() != Status::InProgress {
+        return None;
+    }
+
+    let tiles: Vec<usize> = (0..map.get_tiles().len())
+        .filter(|i| {
+            map.tile_exists(*i) && !map.get_tile(*i).flipped && !map.get_tile(*i).is_flagged()
+        })
+        .collect();
+    let (min_count, radix) = mine_count_range(map);
+    if tiles.is_empty() || tiles.len() > endgame_tile_limit(radix) {
+        return None;
+    }
+    let positions: HashMap<usize, usize> = tiles
+        .iter()
+        .enumerate()
+        .map(|(j, index)| (*index, j))
+        .collect();
+
+    // List every layout of the hidden tiles that fits the clues and the remaining mines.
+    let tiles_flipped: Vec<usize> = (0..map.get_tiles().len())
+        .filter(|i| map.get_tile(*i).flipped && !map.get_tile(*i).is_mine())
+        .collect();
+    let constraints = build_constraints(map, &tiles_flipped, &positions);
+    let mut layouts: Vec<Vec<i32>> = Vec::new();
+    let mut mines: Vec<i32> = vec![0; tiles.len()];
+    'outer: for i in 0..(radix as u64).pow(tiles.len() as u32) {
+        let mut remainder = i;
+        let mut total: u32 = 0;
+        for count in mines.iter_mut() {
+            *count = (remainder % radix as u64) as i32 + min_count;
+            remainder /= radix as u64;
+            total += count.unsigned_abs();
+        }
+        if total != map.get_mines_remaining() {
+            continue;
+        }
+        for (needed, members) in &constraints {
+            if members.iter().map(|j| mines[*j]).sum::<i32>() != *needed {
+                continue 'outer;
+            }
+        }
+        // The game would be over if every safe tile was flipped, so the layout can't be the one.
+        if mines.iter().all(|count| *count != 0) {
+            continue;
+        }
+        layouts.push(mines.clone());
+    }
+    if layouts.is_empty() {
+        return None;
+    }
+
+    // Work out the value each hidden tile would show in each layout, counting flags as mines.
+    let values: Vec<Vec<i32>> = layouts
+        .iter()
+        .map(|layout| {
+            tiles
+                .iter()
+                .map(|index| {
+                    map.get_adjacent(*index)
+                        .iter()
+                        .map(|neighbour| match positions.get(neighbour) {
+                            Some(j) => layout[*j],
+                            None => map.get_tile(*neighbour).flags as i32,
+                        })
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut endgame = Endgame {
+        layouts,
+        values,
+        memo: HashMap::new(),
+    };
+    let all: Vec<u32> = (0..endgame.layouts.len() as u32).collect();
+    let (probability, best) = endgame.search(&all, 0);
+
+    best.map(|j| {
+        let safe: bool = endgame.layouts.iter().all(|layout| layout[j] == 0);
+        (
+            point::from_index(tiles[j], map.get_width()),
+            probability,
+            safe,
+        )
+    })
+}
+
+/// An expectimax search over the layouts of an endgame.
+struct Endgame {
+    /// The mines on each hidden tile for every consistent layout.
+    layouts: Vec<Vec<i32>>,
+    /// The value each hidden tile shows for every consistent layout.
+    values: Vec<Vec<i32>>,
+    /// The chance of winning and best tile for each set of layouts and flipped tiles.
+    memo: HashMap<(Vec<u32>, u32), (f64, Option<usize>)>,
+}
+
+impl Endgame {
+    /// Find the chance of winning given the `layouts` still possible and which hidden tiles
+    /// are `flipped`, along with the tile to flip to get it.
+    fn search(&mut self, layouts: &[u32], flipped: u32) -> (f64, Option<usize>) {
+        let tile_count: usize = self.layouts[0].len();
+
+        // With one layout left every tile is known, so flip any safe tile left.
+        if layouts.len() == 1 {
+            let layout = &self.layouts[layouts[0] as usize];
+            let safe = (0..tile_count).find(|j| flipped & (1 << j) == 0 && layout[*j] == 0);
+            return (1.0, safe);
+        }
+
+        // Layouts with every safe tile flipped are already won, even if their mines are unknown.
+        let playing: Vec<u32> = layouts
+            .iter()
+            .filter(|l| {
+                (0..tile_count)
+                    .any(|j| flipped & (1 << j) == 0 && self.layouts[**l as usize][j] == 0)
+            })
+            .cloned()
+            .collect();
+        if playing.len() < layouts.len() {
+            if playing.is_empty() {
+                return (1.0, None);
+            }
+            let (chance, best) = self.search(&playing, flipped);
+            let won: usize = layouts.len() - playing.len();
+            return (
+                (won as f64 + chance * playing.len() as f64) / layouts.len() as f64,
+                best,
+            );
+        }
+
+        let key = (layouts.to_vec(), flipped);
+        if let Some(result) = self.memo.get(&key) {
+            return *result;
+        }
+
+        let mut best: (f64, Option<usize>) = (0.0, None);
+        for j in 0..tile_count {
+            if flipped & (1 << j) != 0 {
+                continue;
+            }
+
+            // Split the layouts where this tile is safe by the value it would show.
+            let mut outcomes: HashMap<i32, Vec<u32>> = HashMap::new();
+            for l in layouts {
+                if self.layouts[*l as usize][j] == 0 {
+                    outcomes
+                        .entry(self.values[*l as usize][j])
+                        .or_default()
+                        .push(*l);
+                }
+            }
+            let safe: usize = outcomes.values().map(|outcome| outcome.len()).sum();
+            if safe == 0 {
+                continue;
+            }
+
+            let mut outcomes: Vec<Vec<u32>> = outcomes.into_values().collect();
+            outcomes.sort_unstable();
+            let mut probability: f64 = 0.0;
+            for outcome in outcomes {
+                let (chance, _) = self.search(&outcome, flipped | (1 << j));
+                probability += chance * outcome.len() as f64 / layouts.len() as f64;
+            }
+            if best.1.is_none() || probability > best.0 {
+                best = (probability, Some(j));
+            }
+
+            // Flipping a tile that is certain to be safe never hurts, so there is no need to look further.
+            if safe == layouts.len() {
+                break;
+            }
+        }
+
+        self.memo.insert(key, best);
+        best
+    }
+}
+
+/// Perform a random move, leaving out flagged tiles and tiles certain to hold a mine,
+/// since a kind map can only move the mine away from a tile that might be safe.
+/// Working out the chances is slow, so it is only done when the tile picked is next to a flipped tile,
+/// as those are the tiles a clue can make certain.
+fn random_move<R: Rng + ?Sized>(map: &mut Map, rng: &mut R) -> Move {
+    let hidden: Vec<usize> = (0..map.get_tiles().len())
+        .filter(|i| {
+            let tile: &Tile = map.get_tile(*i);
+            map.tile_exists(*i) && !tile.flipped && !tile.is_flagged()
+        })
+        .collect();
+    if hidden.is_empty() {
+        panic!("Failed to find a random tile.");
+    }
+
+    let mut index: usize = hidden[rng.gen_range(0..hidden.len())];
+    let frontier: bool = map
+        .get_adjacent(index)
+        .iter()
+        .any(|neighbour| map.get_tile(*neighbour).flipped);
+    if frontier {
+        if let Some(probabilities) = mine_probabilities(map) {
+            if probabilities[index] > 1.0 - CERTAINTY_TOLERANCE {
+                let candidates: Vec<usize> = hidden
+                    .into_iter()
+                    .filter(|i| probabilities[*i] < 1.0 - CERTAINTY_TOLERANCE)
+                    .collect();
+                index = candidates[rng.gen_range(0..candidates.len())];
+            }
+        }
+    }
+
+    let position = point::from_index(index, map.get_width());
+    map.flip(&position);
+    Move {
+        position,
+        move_type: MoveType::Flip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Graph, Hex, Neighbourhood};
+    use crate::{map, solver};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_simple_solve() {
+        // Define mine positions
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 3, y: 1 },
+            point::Point { x: 4, y: 2 },
+            point::Point { x: 1, y: 1 },
+            point::Point { x: 2, y: 2 },
+            point::Point { x: 4, y: 4 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        // Generate map with these mines.
+        let mut map = map::generate_map_with_mines(5, 5, mines);
+
+        // Flip and flag a couple of tiles.
+        map.flip(&point::Point { x: 0, y: 4 });
+        map.flag(&point::Point { x: 3, y: 1 });
+
+        // Map should be in progrsss
+        assert_eq!(map::Status::InProgress, *map.get_status());
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Should have taken 14 moves.
+        assert_eq!(14, moves.len());
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be completed
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_separate_groups() {
+        // Create a long strip with a mine at each end.
+        let mines: HashSet<point::Point> =
+            [point::Point { x: 0, y: 0 }, point::Point { x: 29, y: 0 }]
+                .iter()
+                .cloned()
+                .collect();
+        let mut map = map::generate_map_with_mines(30, 1, mines);
+
+        // Reveal two clues at each end, leaving too many tiles to consider as one group.
+        for x in [1, 2, 27, 28].iter() {
+            map.reveal(&point::Point { x: *x, y: 0 });
+        }
+
+        // Both groups are solved in the same pass.
+        let mut bitboard = map::Bitboard::from_map(&map);
+        let (moves, guessed) = solver::enumerate_groups(&mut map, &mut bitboard, false);
+        assert!(!guessed);
+        for x in [0, 29].iter() {
+            assert!(moves.contains(&solver::Move {
+                position: point::Point { x: *x, y: 0 },
+                move_type: solver::MoveType::Flag,
+            }));
+        }
+        for x in [3, 26].iter() {
+            assert!(moves.contains(&solver::Move {
+                position: point::Point { x: *x, y: 0 },
+                move_type: solver::MoveType::Flip,
+            }));
+        }
+    }
+
+    #[test]
+    fn test_hard_solve() {
+        // Define mine positions.
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 0, y: 0 },
+            point::Point { x: 1, y: 0 },
+            point::Point { x: 2, y: 0 },
+            point::Point { x: 3, y: 1 },
+            point::Point { x: 4, y: 1 },
+            point::Point { x: 5, y: 1 },
+            point::Point { x: 7, y: 1 },
+            point::Point { x: 8, y: 1 },
+            point::Point { x: 2, y: 2 },
+            point::Point { x: 6, y: 2 },
+            point::Point { x: 9, y: 2 },
+            point::Point { x: 0, y: 4 },
+            point::Point { x: 2, y: 5 },
+            point::Point { x: 4, y: 5 },
+            point::Point { x: 5, y: 5 },
+            point::Point { x: 8, y: 5 },
+            point::Point { x: 9, y: 5 },
+            point::Point { x: 1, y: 6 },
+            point::Point { x: 9, y: 6 },
+            point::Point { x: 0, y: 7 },
+            point::Point { x: 1, y: 7 },
+            point::Point { x: 4, y: 7 },
+            point::Point { x: 4, y: 8 },
+            point::Point { x: 3, y: 9 },
+            point::Point { x: 8, y: 9 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        // Create a map with these mines.
+        let mut map = map::generate_map_with_mines(10, 10, mines);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 6, y: 9 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be solved.
         assert_eq!(map::Status::Complete, *map.get_status());
 
         // Should have taken 61 moves
@@ -580,4 +1886,734 @@ mod tests {
         // Map should be completed.
         assert_ne!(*map.get_status(), map::Status::InProgress);
     }
+
+    #[test]
+    fn test_random_move_skips_certain_mines() {
+        // Both clues around the middle of a 5x1 map only fit a mine there, and the mine on the end is flagged.
+        let mines: HashSet<point::Point> =
+            [point::Point { x: 0, y: 0 }, point::Point { x: 2, y: 0 }]
+                .iter()
+                .cloned()
+                .collect();
+        let mut map = map::generate_map_with_mines(5, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.reveal(&point::Point { x: 3, y: 0 });
+        map.flag(&point::Point { x: 0, y: 0 });
+
+        // Only the unflagged end is left to guess.
+        for seed in 0..10 {
+            let mut guessed = map.clone();
+            let play = random_move(&mut guessed, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(point::Point { x: 4, y: 0 }, play.position);
+        }
+    }
+
+    #[test]
+    fn test_masked_solve() {
+        // Create a 6x6 donut with a 2x2 hole.
+        let mask: Vec<bool> = (0..36).map(|i| ![14, 15, 20, 21].contains(&i)).collect();
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 0, y: 0 },
+            point::Point { x: 5, y: 2 },
+            point::Point { x: 1, y: 5 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map = map::generate_map_with_mines_and_mask(6, 6, mask, mines);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 3, y: 0 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // No move should ever touch the hole.
+        for play in &moves {
+            assert!(map.tile_exists(play.position.to_index(6)));
+        }
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+    }
+
+    #[test]
+    fn test_toroidal_solve() {
+        // Define mine positions, some of which are only solvable by wrapping.
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 0, y: 0 },
+            point::Point { x: 7, y: 3 },
+            point::Point { x: 4, y: 7 },
+            point::Point { x: 3, y: 4 },
+            point::Point { x: 6, y: 1 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let layout = map::Layout {
+            topology: point::Topology::Toroidal,
+            ..map::Layout::new(8, 8)
+        };
+        let mut map = map::generate_map_with_layout_and_mines(layout, mines);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 2, y: 6 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+    }
+
+    #[test]
+    fn test_hex_solve() {
+        // Define mine positions.
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 1, y: 1 },
+            point::Point { x: 5, y: 2 },
+            point::Point { x: 2, y: 5 },
+            point::Point { x: 6, y: 6 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let layout = map::Layout {
+            grid: Arc::new(Hex),
+            ..map::Layout::new(8, 8)
+        };
+        let mut map = map::generate_map_with_layout_and_mines(layout, mines);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 7, y: 0 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+    }
+
+    #[test]
+    fn test_graph_solve() {
+        // A ring of 16 nodes with chords between every third node.
+        let adjacency: Vec<Vec<usize>> =
+            (0..16).map(|i| vec![(i + 1) % 16, (i + 3) % 16]).collect();
+        let mines: HashSet<usize> = [0, 5, 11].iter().cloned().collect();
+        let mut map = map::generate_map_with_graph(adjacency, mines);
+
+        // Flip a safe node.
+        map.flip(&point::Point { x: 8, y: 0 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Every move should address a node.
+        for play in &moves {
+            assert_eq!(0, play.position.y);
+            assert!(play.position.x < 16);
+        }
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+    }
+
+    #[test]
+    fn test_3d_solve() {
+        // Define mine positions.
+        let mines: HashSet<point::Point3> = [
+            point::Point3 { x: 0, y: 0, z: 0 },
+            point::Point3 { x: 4, y: 1, z: 2 },
+            point::Point3 { x: 2, y: 4, z: 4 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map = map::generate_3d_map_with_mines(5, 5, 5, mines);
+
+        // Flip a safe tile.
+        map.flip(&point::Point3 { x: 2, y: 2, z: 0 }.to_point(5));
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+    }
+
+    #[test]
+    fn test_multi_mine_solve() {
+        // Define mine positions and counts.
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 1, y: 1 }, 2),
+            (point::Point { x: 5, y: 1 }, 1),
+            (point::Point { x: 3, y: 4 }, 3),
+            (point::Point { x: 6, y: 6 }, 2),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map =
+            map::generate_map_with_layout_and_mine_counts(map::Layout::new(8, 8), mines, 3);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 0, y: 7 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+
+        // Any tile the solver flagged carries exactly as many flags as mines.
+        if *map.get_status() == map::Status::Complete {
+            for tile in map.get_tiles() {
+                assert!(tile.flags == 0 || tile.flags == tile.mines);
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_mine_solve() {
+        // Define mine positions, with a negative mine among them.
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 1, y: 1 }, 1),
+            (point::Point { x: 5, y: 1 }, -1),
+            (point::Point { x: 3, y: 4 }, 1),
+            (point::Point { x: 6, y: 6 }, -1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map =
+            map::generate_map_with_layout_and_mine_counts(map::Layout::new(8, 8), mines, 1);
+
+        // Flip a safe tile.
+        map.flip(&point::Point { x: 0, y: 7 });
+
+        // Solve the map.
+        let moves = solver::solve(&map);
+
+        // Apply the moves to the map.
+        map.apply_moves(&moves);
+
+        // Map should be finished.
+        assert_ne!(map::Status::InProgress, *map.get_status());
+
+        // Any tile the solver flagged carries a flag of the same sign as its mine.
+        if *map.get_status() == map::Status::Complete {
+            for tile in map.get_tiles() {
+                assert!(tile.flags == 0 || tile.flags == tile.mines);
+            }
+        }
+    }
+
+    #[test]
+    fn test_neighbourhood_solve() {
+        // Define mine positions.
+        let mines: HashSet<point::Point> = [
+            point::Point { x: 1, y: 1 },
+            point::Point { x: 5, y: 2 },
+            point::Point { x: 2, y: 5 },
+            point::Point { x: 6, y: 6 },
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        for neighbourhood in [Neighbourhood::knight(), Neighbourhood::radius(2)].iter() {
+            let layout = map::Layout::with_neighbourhood(8, 8, neighbourhood.clone());
+            let mut map = map::generate_map_with_layout_and_mines(layout, mines.clone());
+
+            // Flip a safe tile.
+            map.flip(&point::Point { x: 7, y: 0 });
+
+            // Solve the map.
+            let moves = solver::solve(&map);
+
+            // Apply the moves to the map.
+            map.apply_moves(&moves);
+
+            // Map should be finished.
+            assert_ne!(map::Status::InProgress, *map.get_status());
+        }
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        // Create a 7x1 map with a mine on each end.
+        let mines: HashSet<point::Point> =
+            [point::Point { x: 0, y: 0 }, point::Point { x: 6, y: 0 }]
+                .iter()
+                .cloned()
+                .collect();
+        let mut map = map::generate_map_with_mines(7, 1, mines);
+
+        // With nothing revealed any two tiles could be mines.
+        assert_eq!(21, solver::count_solutions(&map, u128::MAX));
+
+        // Each clue leaves its mine on either side, independently of the other.
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.reveal(&point::Point { x: 5, y: 0 });
+        assert_eq!(4, solver::count_solutions(&map, u128::MAX));
+        assert_eq!(3, solver::count_solutions(&map, 3));
+
+        // Flags count as placed mines.
+        map.flag(&point::Point { x: 0, y: 0 });
+        assert_eq!(2, solver::count_solutions(&map, u128::MAX));
+
+        // Revealing the rest of the board determines it.
+        map.reveal(&point::Point { x: 3, y: 0 });
+        map.reveal(&point::Point { x: 4, y: 0 });
+        assert_eq!(1, solver::count_solutions(&map, u128::MAX));
+
+        // An unsatisfiable flag leaves no solutions.
+        map.flag(&point::Point { x: 2, y: 0 });
+        assert_eq!(0, solver::count_solutions(&map, u128::MAX));
+
+        // Large boards saturate rather than overflow.
+        let map = map::generate_map_with_total(30, 16, 99, point::Point { x: 0, y: 0 });
+        assert_eq!(u128::MAX, solver::count_solutions(&map, u128::MAX));
+    }
+
+    #[test]
+    fn test_kind_solve() {
+        let click = point::Point { x: 4, y: 4 };
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut wins: u32 = 0;
+        for _ in 0..20 {
+            let mut map = map::generate_map_with_total_and_rng(9, 9, 10, click.clone(), &mut rng);
+            map.set_mode(map::Mode::Kind);
+            map.flip(&click);
+
+            // The map moves its mines with its own randomness, but the moves stay repeatable.
+            let strategy = GuessStrategy::LowestRisk;
+            assert_eq!(
+                solver::solve_with_rng(&map, strategy, &mut StdRng::seed_from_u64(1)),
+                solver::solve_with_rng(&map, strategy, &mut StdRng::seed_from_u64(1))
+            );
+
+            // Solve again after each plan until the map is finished.
+            while *map.get_status() == map::Status::InProgress {
+                for play in solver::solve_with_rng(&map, strategy, &mut rng) {
+                    let before = map.clone();
+                    map.apply_moves(&VecDeque::from(vec![play.clone()]));
+
+                    // Only a tile certain to be a mine loses, or a guess made while another tile
+                    // was certain to be safe, as the solver doesn't look across the whole board.
+                    if *map.get_status() == map::Status::Failed {
+                        let index: usize = play.position.to_index(9);
+                        let probabilities = solver::mine_probabilities(&before).unwrap();
+                        assert!(
+                            probabilities[index] > 1.0 - 1e-9
+                                || (0..81).any(|i| {
+                                    !before.get_tile(i).flipped && probabilities[i] < 1e-9
+                                })
+                        );
+                    }
+                }
+            }
+            if *map.get_status() == map::Status::Complete {
+                wins += 1;
+            }
+        }
+        assert!(wins >= 15);
+    }
+
+    #[test]
+    fn test_capped_count_solutions() {
+        // Stopping at the cap gives the same answer as counting everything and capping after.
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            let mut map = map::generate_map_with_total_and_rng(
+                8,
+                6,
+                10,
+                point::Point { x: 0, y: 0 },
+                &mut rng,
+            );
+            for i in (0..48).step_by(5) {
+                if !map.get_tile(i).is_mine() {
+                    map.reveal(&point::from_index(i, 8));
+                }
+            }
+            let full: u128 = solver::count_solutions(&map, u128::MAX);
+            for cap in 1..6 {
+                assert_eq!(min(full, cap), solver::count_solutions(&map, cap));
+            }
+        }
+    }
+
+    /// Check that the sampled mine `counts` produce the same values on every flipped tile of the `map`.
+    fn assert_consistent(map: &map::Map, counts: HashMap<point::Point, i8>) {
+        let total: u32 = counts
+            .values()
+            .map(|count| count.unsigned_abs() as u32)
+            .sum();
+        let sample = map::generate_map_with_layout_and_mine_counts(
+            map.get_layout(),
+            counts,
+            map.get_tile_capacity(),
+        );
+        assert_eq!(sample.get_mines_remaining(), total);
+        assert_eq!(
+            map.get_mines_remaining()
+                + map
+                    .get_tiles()
+                    .iter()
+                    .map(|tile| tile.flags.unsigned_abs() as u32)
+                    .sum::<u32>(),
+            total
+        );
+        for (i, tile) in map.get_tiles().iter().enumerate() {
+            if tile.flipped {
+                assert_eq!(tile.value, sample.get_tile(i).value);
+                assert!(!sample.get_tile(i).is_mine());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_consistent_layout() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // Create a 7x1 map with a mine on each end, revealing a clue next to each.
+        let mines: HashSet<point::Point> =
+            [point::Point { x: 0, y: 0 }, point::Point { x: 6, y: 0 }]
+                .iter()
+                .cloned()
+                .collect();
+        let mut map = map::generate_map_with_mines(7, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.reveal(&point::Point { x: 5, y: 0 });
+
+        // Each of the 4 consistent layouts is drawn about as often as the others.
+        let mut seen: HashMap<Vec<u16>, u32> = HashMap::new();
+        for _ in 0..4000 {
+            let mut layout: Vec<u16> = solver::sample_consistent_layout(&map, &mut rng)
+                .iter()
+                .map(|mine| mine.x)
+                .collect();
+            layout.sort_unstable();
+            *seen.entry(layout).or_insert(0) += 1;
+        }
+        assert_eq!(4, seen.len());
+        assert!(seen.values().all(|count| *count > 800 && *count < 1200));
+
+        // Samples agree with a partly solved board, including flags.
+        let mut map = map::generate_map_with_total(16, 16, 40, point::Point { x: 8, y: 8 });
+        map.flip(&point::Point { x: 8, y: 8 });
+        let moves = solver::solve(&map);
+        for play in moves.iter().take(moves.len() / 2) {
+            if play.move_type == solver::MoveType::Flag {
+                map.apply_moves(&VecDeque::from(vec![play.clone()]));
+            }
+        }
+        for _ in 0..20 {
+            assert_consistent(&map, solver::sample_consistent_mine_counts(&map, &mut rng));
+        }
+
+        // Samples hold several or negative mines where the map allows them.
+        let layout = map::Layout::new(8, 8);
+        let mut map =
+            map::generate_map_with_capacity(layout.clone(), 20, 3, point::Point { x: 0, y: 0 });
+        map.flip(&point::Point { x: 0, y: 0 });
+        for _ in 0..20 {
+            assert_consistent(&map, solver::sample_consistent_mine_counts(&map, &mut rng));
+        }
+        let mut map =
+            map::generate_map_with_negative_mines(layout, 12, 0.5, point::Point { x: 0, y: 0 });
+        map.flip(&point::Point { x: 0, y: 0 });
+        for _ in 0..20 {
+            assert_consistent(&map, solver::sample_consistent_mine_counts(&map, &mut rng));
+        }
+    }
+
+    /// Write `moves` compactly, as `F` for a flip, `P` for a flag or `N` for a negative flag
+    /// followed by the position.
+    fn notation(moves: &VecDeque<solver::Move>) -> String {
+        moves
+            .iter()
+            .map(|m| {
+                let symbol = match m.move_type {
+                    solver::MoveType::Flip => "F",
+                    solver::MoveType::Flag => "P",
+                    solver::MoveType::NegativeFlag => "N",
+                };
+                format!("{}{},{}", symbol, m.position.x, m.position.y)
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Create an 8x8 map with the given `mines` that has been opened from the top left corner.
+    fn golden_map(mines: &[(u16, u16)]) -> map::Map {
+        let mines: HashSet<point::Point> = mines
+            .iter()
+            .map(|(x, y)| point::Point { x: *x, y: *y })
+            .collect();
+        let mut map = map::generate_map_with_mines(8, 8, mines);
+        map.flip(&point::Point { x: 0, y: 0 });
+        map
+    }
+
+    #[test]
+    fn test_golden_solves() {
+        // Boards that need guesses, along with the exact moves expected for each strategy.
+        let first: &[(u16, u16)] = &[
+            (1, 1),
+            (0, 2),
+            (2, 2),
+            (6, 2),
+            (7, 3),
+            (1, 5),
+            (2, 5),
+            (6, 5),
+            (7, 5),
+            (2, 6),
+            (4, 6),
+            (0, 7),
+        ];
+        let second: &[(u16, u16)] = &[
+            (5, 0),
+            (0, 1),
+            (6, 1),
+            (5, 2),
+            (6, 2),
+            (7, 3),
+            (2, 4),
+            (0, 5),
+            (2, 5),
+            (5, 5),
+            (7, 6),
+            (7, 7),
+        ];
+        let cases = [
+            (
+                first,
+                GuessStrategy::LowestRisk,
+                "F1,0 F2,0 F2,1 F3,0 P1,1 P2,2 P6,2 F6,1 F3,2 F5,2 P7,3 F2,3 F5,3 F6,3 P1,5 P2,5 \
+                 P6,5 P7,5 F0,0 P0,2 F1,2 F0,3 F0,4 F0,5 F3,6 F5,6 F6,6 F2,7 P2,6 P4,6 F3,6 F5,6 \
+                 F2,7",
+            ),
+            (
+                first,
+                GuessStrategy::WinProbability,
+                "F7,0 P2,2 P6,2 F6,1 F3,2 F5,2 P7,3 F2,3 F5,3 F6,3 P1,5 P2,5 P6,5 P7,5 F1,4 F0,5 \
+                 P0,2 F1,0 F0,1 F5,6 F2,7 P1,1 P2,6 F2,7 P4,6 F5,5 P0,7 F3,6 F5,6",
+            ),
+            (
+                second,
+                GuessStrategy::LowestRisk,
+                "F1,0 F2,0 F2,1 P0,1 F1,1 F0,2 P2,4 F2,3 F3,3 F5,1 F5,3 F5,4 F3,5 F4,5 P5,0 P5,2 \
+                 P2,5 P5,5 F5,4 F3,5 F4,5 F2,6 P7,6 P7,7 P6,2 P0,5 F6,5 P7,3 F6,3 F6,0 F7,0 F7,1 \
+                 P6,1",
+            ),
+            (
+                second,
+                GuessStrategy::WinProbability,
+                "F7,0 F0,7 P7,6 P7,7 F1,5 F3,5 F4,5 F6,5 F7,5 P0,5 P2,5 P5,5 F4,5 F6,5 F4,4 F5,4 \
+                 P7,3 P2,4 F3,3 P5,2 P6,2 F6,3 F1,5 P0,1 F1,1 F0,2 F4,2 P5,0 F6,0 F7,1 P6,1",
+            ),
+        ];
+
+        for (mines, strategy, expected) in cases.iter() {
+            let mut map = golden_map(mines);
+            let moves = solver::solve_with_rng(&map, *strategy, &mut StdRng::seed_from_u64(1));
+            assert_eq!(*expected, notation(&moves));
+
+            // Kind and cruel maps are planned against the mines where they are now.
+            for mode in [map::Mode::Kind, map::Mode::Cruel].iter() {
+                let mut moving = map.clone();
+                moving.set_mode(*mode);
+                let moves =
+                    solver::solve_with_rng(&moving, *strategy, &mut StdRng::seed_from_u64(1));
+                assert_eq!(*expected, notation(&moves));
+            }
+
+            map.apply_moves(&moves);
+            assert_eq!(map::Status::Complete, *map.get_status());
+        }
+    }
+
+    #[test]
+    fn test_win_probability_move() {
+        // Create an 8x1 map with a mine on each end, revealing a clue next to the first.
+        let mines: HashSet<point::Point> =
+            [point::Point { x: 0, y: 0 }, point::Point { x: 7, y: 0 }]
+                .iter()
+                .cloned()
+                .collect();
+        let mut map = map::generate_map_with_mines(8, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+
+        // The clue splits one mine between its neighbours, leaving the other to the rest.
+        let probabilities = solver::mine_probabilities(&map).unwrap();
+        let expected = [0.5, 0.0, 0.5, 0.2, 0.2, 0.2, 0.2, 0.2];
+        for (probability, expected) in probabilities.iter().zip(expected.iter()) {
+            assert!((probability - expected).abs() < 1e-9);
+        }
+
+        // Flags that leave no room for the clue's mine rule out every layout.
+        let mut flagged = map.clone();
+        flagged.flag(&point::Point { x: 3, y: 0 });
+        flagged.flag(&point::Point { x: 4, y: 0 });
+        assert_eq!(None, solver::mine_probabilities(&flagged));
+        assert!(win_probability_move(&mut flagged).is_none());
+
+        // Rather than a coin flip next to the clue, guess the end tile as it is the most likely to open up.
+        let (play, probability) = win_probability_move(&mut map).unwrap();
+        assert_eq!(point::Point { x: 7, y: 0 }, play.position);
+        assert!((probability - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_solve_endgame() {
+        // Create a 4x1 map with one mine and nothing flipped.
+        let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+        let map = map::generate_map_with_mines(4, 1, mines);
+
+        // An end tile is safe 3 times out of 4 and then reveals the rest,
+        // while the tiles in the middle can leave a coin flip.
+        let (position, probability) = solver::solve_endgame(&map).unwrap();
+        assert_eq!(point::Point { x: 0, y: 0 }, position);
+        assert!((probability - 0.75).abs() < 1e-9);
+
+        // Once the mine is known the game is won.
+        let mut map = map.clone();
+        map.flip(&point::Point { x: 0, y: 0 });
+        let (position, probability) = solver::solve_endgame(&map).unwrap();
+        assert_eq!(point::Point { x: 3, y: 0 }, position);
+        assert_eq!(1.0, probability);
+
+        // Large positions are left to the other strategies.
+        let map = map::generate_map_with_total(8, 8, 10, point::Point { x: 0, y: 0 });
+        assert!(solver::solve_endgame(&map).is_none());
+
+        // Tiles that can hold several mines, including negative ones, have more layouts to search,
+        // so fewer tiles are searched.
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 0, y: 0 }, 3),
+            (point::Point { x: 5, y: 1 }, -2),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let map = map::generate_map_with_layout_and_mine_counts(map::Layout::new(6, 2), mines, 3);
+        assert_eq!(4, endgame_tile_limit(7));
+        assert!(solver::solve_endgame(&map).is_none());
+    }
+
+    #[test]
+    fn test_endgame_won_without_knowing_the_mines() {
+        // Two mine tiles share both of their neighbours, so a clue and a hidden tile
+        // each see 3 mines whichever of them holds 2.
+        let graph = Graph::new(vec![vec![2, 3], vec![2, 3], vec![], vec![]]);
+        let mines: HashMap<point::Point, i8> = [
+            (point::Point { x: 0, y: 0 }, 2),
+            (point::Point { x: 1, y: 0 }, 1),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let mut map =
+            map::generate_map_with_layout_and_mine_counts(map::Layout::from_graph(graph), mines, 2);
+        map.reveal(&point::Point { x: 2, y: 0 });
+
+        // Flipping the last safe tile wins, though it can't tell the layouts apart.
+        let (position, probability) = solver::solve_endgame(&map).unwrap();
+        assert_eq!(point::Point { x: 3, y: 0 }, position);
+        assert_eq!(1.0, probability);
+    }
+
+    #[test]
+    fn test_risk_of_large_groups() {
+        // Mines alternate between the top and bottom rows with the middle row revealed,
+        // so the group of hidden tiles fits 264 layouts and every tile is a mine in half of them.
+        let mines: HashSet<point::Point> = (0..8)
+            .map(|x| point::Point {
+                x,
+                y: if x % 2 == 0 { 0 } else { 2 },
+            })
+            .collect();
+        let mut map = map::generate_map_with_mines(8, 3, mines);
+        for x in 0..8 {
+            map.reveal(&point::Point { x, y: 1 });
+        }
+
+        let group: HashSet<usize> = (0..24).filter(|i| i / 8 != 1).collect();
+        assert_eq!(264, tally_group(&map, &group).valid_permutations);
+        let nominations = evaluate_group(&map, &group);
+        assert_eq!(1, nominations.len());
+        assert!(nominations.iter().all(|(_, risk, _)| *risk == 127));
+
+        // No tile is safe, so nothing is flipped without a guess.
+        let mut bitboard = Bitboard::from_map(&map);
+        let (moves, guessed) = enumerate_groups(&mut map, &mut bitboard, false);
+        assert!(moves.is_empty());
+        assert!(!guessed);
+    }
+
+    #[test]
+    fn test_safe_moves_are_not_guesses() {
+        // A long strip with a mine on the end, and a clue next to it.
+        let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(30, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+
+        // The clue holds the only mine, so the far tiles are safe and reveal the rest.
+        let mut rng = StdRng::seed_from_u64(1);
+        let (moves, guesses) = solve_noting_guesses(&map, GuessStrategy::WinProbability, &mut rng);
+        assert!(guesses.is_empty());
+        assert!(find_guesses(&map, &moves).is_empty());
+        map.apply_moves(&moves);
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
+
+    #[test]
+    fn test_solve_with_wrong_flag() {
+        // The only mine is on one end of a 4x1 map, but the flag on the other end uses it up.
+        let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(4, 1, mines);
+        map.reveal(&point::Point { x: 1, y: 0 });
+        map.flag(&point::Point { x: 3, y: 0 });
+
+        // The mine can't be flagged, so the solver stops rather than planning the flag forever.
+        let moves = solver::solve_with_rng(
+            &map,
+            GuessStrategy::LowestRisk,
+            &mut StdRng::seed_from_u64(0),
+        );
+        map.apply_moves(&moves);
+        assert_eq!(map::Status::InProgress, *map.get_status());
+        assert!(!map.get_tile(0).is_flagged());
+
+        // Without the flag the map is solved.
+        let mut map = map.without_flags();
+        let moves = solver::solve_with_rng(
+            &map,
+            GuessStrategy::LowestRisk,
+            &mut StdRng::seed_from_u64(0),
+        );
+        map.apply_moves(&moves);
+        assert_eq!(map::Status::Complete, *map.get_status());
+    }
 }
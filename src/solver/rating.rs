@@ -0,0 +1,177 @@
+//! Measures how hard a board is to play.
+
+use super::{count_guesses, solve_with_rng, GuessStrategy};
+use crate::map::Map;
+use crate::point;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fmt;
+
+/// How hard a board is to play.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Rating {
+    /// The fewest flips needed to clear the board without flagging, see `three_bv`.
+    pub three_bv: u32,
+    /// The number of moves the solver made that might have been wrong given what it could see,
+    /// where 0 means the board never needs a guess.
+    pub guesses: u32,
+    /// The density of mines, on the scale used by `map::generate_map_with_difficulty`.
+    pub difficulty: u8,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "3bv:        {}", self.three_bv)?;
+        writeln!(f, "guesses:    {}", self.guesses)?;
+        write!(f, "difficulty: {}", self.difficulty)
+    }
+}
+
+/// Checks if flipping the tile at `index` would flip its neighbours too.
+fn is_opening(map: &Map, index: usize) -> bool {
+    let tile = map.get_tile(index);
+    !tile.is_mine()
+        && tile.value == 0
+        && !(map.has_negative_mines()
+            && map
+                .get_adjacent(index)
+                .iter()
+                .any(|neighbour| map.get_tile(*neighbour).is_mine()))
+}
+
+/// Count the fewest flips needed to clear the board without flagging, known as its "3BV".
+/// Each opening of connected empty tiles takes one flip, as does every safe tile
+/// that isn't on the edge of an opening. Flipped tiles count too, so this is a property of the board.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// // A 5x1 map with a mine in the middle has an opening at each end.
+/// let mines: HashSet<point::Point> = [point::Point { x: 2, y: 0 }].iter().cloned().collect();
+/// let map = map::generate_map_with_mines(5, 1, mines);
+/// assert_eq!(2, solver::three_bv(&map));
+/// ```
+pub fn three_bv(map: &Map) -> u32 {
+    let size: usize = map.get_tiles().len();
+    let mut covered: Vec<bool> = vec![false; size];
+    let mut clicks: u32 = 0;
+
+    // Each opening takes one flip and covers every tile around it.
+    for start in 0..size {
+        if covered[start] || !map.tile_exists(start) || !is_opening(map, start) {
+            continue;
+        }
+        clicks += 1;
+        covered[start] = true;
+        let mut stack: Vec<usize> = vec![start];
+        while let Some(index) = stack.pop() {
+            for neighbour in map.get_adjacent(index) {
+                if !covered[*neighbour] {
+                    covered[*neighbour] = true;
+                    if is_opening(map, *neighbour) {
+                        stack.push(*neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    // The remaining safe tiles each need their own flip.
+    clicks
+        + (0..size)
+            .filter(|i| map.tile_exists(*i) && !covered[*i] && !map.get_tile(*i).is_mine())
+            .count() as u32
+}
+
+/// Find the first tile in index order that would flip its neighbours too, making it a safe first click.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// use std::collections::HashSet;
+///
+/// let mines: HashSet<point::Point> = [point::Point { x: 0, y: 0 }].iter().cloned().collect();
+/// let map = map::generate_map_with_mines(4, 4, mines);
+/// assert_eq!(Some(point::Point { x: 2, y: 0 }), solver::find_opening(&map));
+/// ```
+pub fn find_opening(map: &Map) -> Option<point::Point> {
+    (0..map.get_tiles().len())
+        .find(|i| map.tile_exists(*i) && is_opening(map, *i))
+        .map(|index| point::from_index(index, map.get_width()))
+}
+
+/// Rate how hard the `map` is to play from its current state.
+/// If nothing has been flipped yet, the first opening is flipped for free, as if it were the first click.
+/// The solver's guesses are seeded, so the same map always gets the same rating.
+///
+/// ```
+/// use casspir::{map, point, solver};
+/// let click = point::Point { x: 4, y: 4 };
+/// let mut map = map::generate_map_with_total(9, 9, 10, click.clone());
+/// map.flip(&click);
+/// let rating = solver::rate(&map);
+/// assert!(rating.three_bv > 0);
+/// assert_eq!(rating, solver::rate(&map));
+/// ```
+pub fn rate(map: &Map) -> Rating {
+    let mut start = map.clone();
+    if start.get_tiles_flipped() == 0 {
+        if let Some(position) = find_opening(map) {
+            start.flip(&position);
+        }
+    }
+    let moves = solve_with_rng(
+        &start,
+        GuessStrategy::LowestRisk,
+        &mut StdRng::seed_from_u64(0),
+    );
+    let guesses: u32 = count_guesses(&start, &moves);
+
+    // Invert the mine probability used when generating maps with a difficulty.
+    let density: f64 = map.get_total_mines() as f64 / map.get_tile_count() as f64;
+    let difficulty: f64 = (density * 512.0 - 20.0).round();
+
+    Rating {
+        three_bv: three_bv(map),
+        guesses,
+        difficulty: difficulty.clamp(0.0, 255.0) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::point::Point;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_three_bv() {
+        // A 4x4 map with a mine in one corner is cleared by one flip of the opening.
+        let mines: HashSet<Point> = [Point { x: 0, y: 0 }].iter().cloned().collect();
+        let map = map::generate_map_with_mines(4, 4, mines);
+        assert_eq!(1, three_bv(&map));
+
+        // Mines along the middle column leave an opening on the right, a column of
+        // numbers on the left that each need a flip, and the right column's edge covered.
+        let mines: HashSet<Point> = (0..4).map(|y| Point { x: 1, y }).collect();
+        let map = map::generate_map_with_mines(5, 4, mines);
+        assert_eq!(5, three_bv(&map));
+    }
+
+    #[test]
+    fn test_rate() {
+        // The same mine layout is rated the same from the start and after the first click.
+        let mines: HashSet<Point> = [Point { x: 0, y: 0 }].iter().cloned().collect();
+        let mut map = map::generate_map_with_mines(4, 4, mines);
+        let rating = rate(&map);
+        assert_eq!(0, rating.guesses);
+        assert_eq!(1, rating.three_bv);
+
+        map.flip(&Point { x: 3, y: 3 });
+        assert_eq!(rating, rate(&map));
+
+        // One mine in 16 tiles is the difficulty that would generate that density.
+        assert_eq!((512.0f64 / 16.0 - 20.0) as u8, rating.difficulty);
+    }
+}